@@ -0,0 +1,162 @@
+use chrono::NaiveDate;
+use std::path::Path;
+
+use crate::github::activity::Activity;
+
+/// File formats activity can be saved in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ActivityFormat {
+    /// Serialization of the whole [`Activity`], including contributions by kind.
+    Json,
+    /// `date,count` lines with the number of contributions of every day in
+    /// the activity date range.
+    Csv,
+}
+
+impl ActivityFormat {
+    /// Guess the format from the extension of a file, defaulting to JSON.
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension().and_then(|extension| extension.to_str()) {
+            Some(extension) if extension.eq_ignore_ascii_case("csv") => ActivityFormat::Csv,
+            _ => ActivityFormat::Json,
+        }
+    }
+}
+
+/// Errors raised while loading activity files.
+#[derive(thiserror::Error, Debug)]
+pub enum ArchiveError {
+    #[error("invalid activity JSON: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("invalid activity CSV at line {line}: {message}")]
+    Csv { line: usize, message: String },
+}
+
+const CSV_HEADER: &str = "date,count";
+
+/// Represent `activity` in the given format.
+pub fn save_activity(activity: &Activity, format: ActivityFormat) -> Result<String, ArchiveError> {
+    match format {
+        ActivityFormat::Json => Ok(serde_json::to_string_pretty(activity)?),
+        ActivityFormat::Csv => {
+            let (start, end) = activity.date_range;
+            let mut builder = String::new();
+            builder.push_str(CSV_HEADER);
+            builder.push('\n');
+            for date in start.iter_days().take_while(|date| *date <= end) {
+                builder.push_str(&format!("{},{}\n", date, activity.contributions_on(date)));
+            }
+            Ok(builder)
+        }
+    }
+}
+
+/// Parse activity previously saved with [`save_activity`].
+/// The date range of CSV files spans from their first to their last date.
+pub fn load_activity(text: &str, format: ActivityFormat) -> Result<Activity, ArchiveError> {
+    match format {
+        ActivityFormat::Json => Ok(serde_json::from_str(text)?),
+        ActivityFormat::Csv => {
+            let mut days: Vec<(NaiveDate, u32)> = Vec::new();
+            for (index, line) in text.lines().enumerate() {
+                let line = line.trim();
+                if line.is_empty() || (index == 0 && line == CSV_HEADER) {
+                    continue;
+                }
+                let csv_error = |message: String| ArchiveError::Csv {
+                    line: index + 1,
+                    message,
+                };
+                let (date, count) = line
+                    .split_once(',')
+                    .ok_or_else(|| csv_error(format!("expected `{}`", CSV_HEADER)))?;
+                let date = NaiveDate::parse_from_str(date.trim(), "%Y-%m-%d")
+                    .map_err(|error| csv_error(format!("invalid date `{}`: {}", date, error)))?;
+                let count = count
+                    .trim()
+                    .parse()
+                    .map_err(|error| csv_error(format!("invalid count `{}`: {}", count, error)))?;
+                days.push((date, count));
+            }
+
+            let start = days.iter().map(|(date, _)| *date).min();
+            let end = days.iter().map(|(date, _)| *date).max();
+            match start.zip(end) {
+                Some(date_range) => Ok(Activity::from_daily_contributions(date_range, days)),
+                None => Err(ArchiveError::Csv {
+                    line: 1,
+                    message: "no days found".to_string(),
+                }),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample_activity() -> Activity {
+        let start = NaiveDate::from_ymd_opt(2024, 12, 28).unwrap();
+        let end = NaiveDate::from_ymd_opt(2025, 1, 3).unwrap();
+        Activity::from_daily_contributions(
+            (start, end),
+            vec![
+                (NaiveDate::from_ymd_opt(2024, 12, 30).unwrap(), 4),
+                (NaiveDate::from_ymd_opt(2025, 1, 2).unwrap(), 1),
+            ],
+        )
+    }
+
+    #[test]
+    fn test_format_from_path() {
+        assert_eq!(
+            ActivityFormat::from_path(Path::new("activity.CSV")),
+            ActivityFormat::Csv
+        );
+        assert_eq!(
+            ActivityFormat::from_path(Path::new("activity.json")),
+            ActivityFormat::Json
+        );
+        assert_eq!(
+            ActivityFormat::from_path(Path::new("activity")),
+            ActivityFormat::Json
+        );
+    }
+
+    #[test]
+    fn test_json_round_trip() {
+        let activity = sample_activity();
+        let json = save_activity(&activity, ActivityFormat::Json).unwrap();
+        assert!(json.contains("\"2025-W01\""));
+
+        let loaded = load_activity(&json, ActivityFormat::Json).unwrap();
+        assert_eq!(loaded.date_range, activity.date_range);
+        assert_eq!(loaded.contributions, activity.contributions);
+    }
+
+    #[test]
+    fn test_csv_round_trip() {
+        let activity = sample_activity();
+        let csv = save_activity(&activity, ActivityFormat::Csv).unwrap();
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines.len(), 8);
+        assert_eq!(lines[0], "date,count");
+        assert_eq!(lines[1], "2024-12-28,0");
+        assert_eq!(lines[3], "2024-12-30,4");
+
+        let loaded = load_activity(&csv, ActivityFormat::Csv).unwrap();
+        assert_eq!(loaded.date_range, activity.date_range);
+        assert_eq!(loaded.contributions, activity.contributions);
+        assert_eq!(loaded.as_matrix(), activity.as_matrix());
+    }
+
+    #[test]
+    fn test_csv_errors() {
+        let result = load_activity("date,count\n2024-01-01,three\n", ActivityFormat::Csv);
+        assert!(matches!(result, Err(ArchiveError::Csv { line: 2, .. })));
+
+        let result = load_activity("date,count\n", ActivityFormat::Csv);
+        assert!(matches!(result, Err(ArchiveError::Csv { .. })));
+    }
+}
@@ -1,30 +1,856 @@
-use crate::github::activity;
-use crate::openscad::generators::generate_data_source;
+use crate::github::activity::{
+    self, Activity, ActivityOptions, ContributionKind, ContributionLevel, ContributionTotals,
+    MergeMode, WeekStart,
+};
+use crate::github::client::GitHubClient;
+use crate::github::error::GitHubError;
+use crate::heights::{HeightMapping, HeightOptions};
+use crate::mesh::{Color, Mesh, Part, TrophyShape, trophy_mesh, trophy_part_meshes};
+use crate::openscad::generators::{
+    Dataset, ScadValue, generate_data_source, generate_datasets_source, generate_layers_source,
+};
+use crate::stats::{ActivityStats, Streak};
 
 use chrono::{Datelike, NaiveDate};
 
+/// Name of the OpenSCAD variable holding the matrix of contributions
+/// of the given kind.
+fn kind_variable_name(kind: ContributionKind) -> &'static str {
+    match kind {
+        ContributionKind::Commit => "rawCommits",
+        ContributionKind::PullRequest => "rawPullRequests",
+        ContributionKind::Issue => "rawIssues",
+        ContributionKind::PullRequestReview => "rawReviews",
+        ContributionKind::Repository => "rawRepositories",
+    }
+}
+
+/// Values written in the matrices of the generated program.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum MatrixValues {
+    /// Number of contributions made each day.
+    #[default]
+    Counts,
+    /// Contribution level, from 0 to 4, of each day so that a few very
+    /// busy days do not dwarf the rest of the activity.
+    Levels,
+}
+
+/// Options controlling how activity is laid out in the generated program.
+#[derive(Debug, Clone, Default)]
+pub struct RenderOptions {
+    /// First day of the weeks, each week being a row of the matrices.
+    pub week_start: WeekStart,
+    /// Whether heights are computed from contribution counts or levels.
+    pub values: MatrixValues,
+    /// How values are mapped to pillar heights.
+    pub heights: HeightOptions,
+    /// Also emit the [`ActivityStats`] as variables, see [`stats_variables`].
+    pub stats: bool,
+    /// Also emit the totals reported by GitHub, including private
+    /// contributions, as variables, see [`totals_variables`].
+    pub totals: bool,
+}
+
+/// OpenSCAD variables with the statistics of an activity, templates can
+/// emboss e.g. `statsTxt`: "1,234 contributions · 87-day streak".
+pub fn stats_variables(stats: &ActivityStats) -> Vec<(String, String)> {
+    let streak_days = |streak: Option<Streak>| streak.map_or(0, |streak| streak.days());
+    vec![
+        ("totalContributions", stats.total_contributions.to_scad()),
+        ("activeDays", stats.active_days.to_scad()),
+        (
+            "averagePerActiveDay",
+            ((stats.average_per_active_day * 100.0).round() / 100.0).to_scad(),
+        ),
+        ("longestStreak", streak_days(stats.longest_streak).to_scad()),
+        ("currentStreak", streak_days(stats.current_streak).to_scad()),
+        (
+            "busiestDayCount",
+            stats
+                .busiest_day
+                .map_or(0, |day| day.contributions)
+                .to_scad(),
+        ),
+        (
+            "busiestWeekCount",
+            stats
+                .busiest_week
+                .map_or(0, |week| week.contributions)
+                .to_scad(),
+        ),
+        (
+            "weekdayContributions",
+            stats.weekday_contributions.to_scad(),
+        ),
+        ("statsTxt", stats.summary().to_scad()),
+    ]
+    .into_iter()
+    .map(|(name, expression)| (name.to_string(), expression))
+    .collect()
+}
+
+/// OpenSCAD variables with the totals GitHub reports for an activity,
+/// templates can emboss e.g. `privateTxt`: "plus 12 private contributions".
+/// Totals which are not available are `undef` and `privateTxt` is empty.
+pub fn totals_variables(totals: Option<ContributionTotals>) -> Vec<(String, String)> {
+    let private_text = match totals {
+        Some(totals) if totals.restricted_contributions == 1 => {
+            "plus 1 private contribution".to_string()
+        }
+        Some(totals) if totals.restricted_contributions > 1 => format!(
+            "plus {} private contributions",
+            totals.restricted_contributions
+        ),
+        _ => String::new(),
+    };
+    vec![
+        (
+            "calendarContributions",
+            totals.map(|totals| totals.total_contributions).to_scad(),
+        ),
+        (
+            "restrictedContributions",
+            totals
+                .map(|totals| totals.restricted_contributions)
+                .to_scad(),
+        ),
+        (
+            "hasRestrictedContributions",
+            totals
+                .map(|totals| totals.has_any_restricted_contributions)
+                .to_scad(),
+        ),
+        ("privateTxt", private_text.to_scad()),
+    ]
+    .into_iter()
+    .map(|(name, expression)| (name.to_string(), expression))
+    .collect()
+}
+
+/// Variables summarizing `activity` requested in `render_options`.
+fn summary_variables(activity: &Activity, render_options: &RenderOptions) -> Vec<(String, String)> {
+    let mut variables = Vec::new();
+    if render_options.stats {
+        variables.extend(stats_variables(&ActivityStats::from_activity(activity)));
+    }
+    if render_options.totals {
+        variables.extend(totals_variables(activity.totals));
+    }
+    variables
+}
+
+fn levels_as_values(matrix: Vec<Vec<Option<ContributionLevel>>>) -> Vec<Vec<Option<u32>>> {
+    matrix
+        .into_iter()
+        .map(|week| {
+            week.into_iter()
+                .map(|level| level.map(ContributionLevel::value))
+                .collect()
+        })
+        .collect()
+}
+
+/// How the calendars of two activities are laid out on a comparison
+/// trophy.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ComparisonLayout {
+    /// Each calendar on one side of the base, mirroring the other.
+    #[default]
+    Mirrored,
+    /// Weeks of both calendars alternating along the base.
+    Interleaved,
+}
+
+impl ComparisonLayout {
+    fn name(self) -> &'static str {
+        match self {
+            ComparisonLayout::Mirrored => "mirrored",
+            ComparisonLayout::Interleaved => "interleaved",
+        }
+    }
+}
+
+/// Group of users whose activity is represented by a single trophy.
+#[derive(Debug, Clone)]
+pub struct Team {
+    /// Name printed on the trophy instead of a user handle.
+    pub name: String,
+    /// Handles of the team members.
+    pub members: Vec<String>,
+    /// How the contributions of the members are combined.
+    pub merge: MergeMode,
+    /// Maximum number of members whose activity is downloaded at once.
+    pub parallelism: usize,
+}
+
+impl Team {
+    pub fn new(name: String, members: Vec<String>) -> Self {
+        Team {
+            name,
+            members,
+            merge: MergeMode::default(),
+            parallelism: 4,
+        }
+    }
+}
+
+/// Represent already downloaded activity as an OpenSCAD program,
+/// labelled with `user_handle`.
+/// Matrices hold the height, in millimetres, of the pillar of each day.
+/// Heights of every matrix are scaled relative to the overall activity.
+/// Days of the first and last weeks out of the activity date range
+/// are written as `undef`.
+pub fn render_openscad(
+    activity: &Activity,
+    user_handle: String,
+    maybe_static_code: Option<String>,
+    render_options: &RenderOptions,
+) -> String {
+    render_labelled_openscad(
+        activity,
+        user_handle,
+        vec![],
+        maybe_static_code,
+        render_options,
+    )
+}
+
+/// Represent the merged activity of `team` as an OpenSCAD program
+/// labelled with the team name. Members are listed in the `teamMembers`
+/// array and the `membersTxt` text.
+pub fn render_team_openscad(
+    activity: &Activity,
+    team: &Team,
+    maybe_static_code: Option<String>,
+    render_options: &RenderOptions,
+) -> String {
+    let members_variables = vec![
+        ("teamMembers".to_string(), team.members.to_scad()),
+        ("membersTxt".to_string(), team.members.join(", ").to_scad()),
+    ];
+    render_labelled_openscad(
+        activity,
+        team.name.clone(),
+        members_variables,
+        maybe_static_code,
+        render_options,
+    )
+}
+
+/// Matrix with the values of each day of `activity`, laid out as
+/// requested in `render_options`.
+fn activity_values(activity: &Activity, render_options: &RenderOptions) -> Vec<Vec<Option<u32>>> {
+    let week_start = render_options.week_start;
+    match render_options.values {
+        MatrixValues::Counts => activity.as_calendar_matrix(week_start),
+        MatrixValues::Levels => levels_as_values(activity.as_level_matrix(week_start)),
+    }
+}
+
+/// Text describing the date range of `activity`, e.g: "2024/1 - 2024/12".
+fn span_text(activity: &Activity) -> String {
+    let (start_date, end_date) = activity.date_range;
+    format!(
+        "{}/{} - {}/{}",
+        start_date.year(),
+        start_date.month(),
+        end_date.year(),
+        end_date.month()
+    )
+}
+
+fn render_labelled_openscad(
+    activity: &Activity,
+    label: String,
+    mut variables: Vec<(String, String)>,
+    maybe_static_code: Option<String>,
+    render_options: &RenderOptions,
+) -> String {
+    let week_start = render_options.week_start;
+
+    let matrix_of_kind = |kind| match render_options.values {
+        MatrixValues::Counts => activity.as_calendar_matrix_of_kind(kind, week_start),
+        MatrixValues::Levels => activity
+            .as_level_matrix_of_kind(kind, week_start)
+            .map(levels_as_values),
+    };
+
+    let result_as_simple_matrix = activity_values(activity, render_options);
+    let heights = HeightMapping::fit(render_options.heights, &result_as_simple_matrix);
+    let kind_matrices = ContributionKind::ALL
+        .into_iter()
+        .filter_map(|kind| {
+            matrix_of_kind(kind)
+                .map(|matrix| (kind_variable_name(kind).to_string(), heights.apply(&matrix)))
+        })
+        .collect();
+    variables.extend(summary_variables(activity, render_options));
+    generate_data_source(
+        label,
+        span_text(activity),
+        heights.apply(&result_as_simple_matrix),
+        kind_matrices,
+        variables,
+        maybe_static_code,
+    )
+}
+
+/// Build the mesh of the trophy of `activity`, with the base and
+/// pillars of [`render_openscad`] but without its texts and logo, to be
+/// written as e.g. STL without going through OpenSCAD.
+pub fn render_mesh(
+    activity: &Activity,
+    shape: &TrophyShape,
+    render_options: &RenderOptions,
+) -> Mesh {
+    let values = activity_values(activity, render_options);
+    let heights = HeightMapping::fit(render_options.heights, &values);
+    trophy_mesh(&heights.apply(&values), shape)
+}
+
+/// Colours of the parts built by [`render_parts`] and [`openscad_parts`],
+/// the GitHub contribution graph palette by default.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Palette {
+    pub base: Color,
+    /// Colour of every pillar unless they are graded by level.
+    pub pillars: Color,
+    /// Colour of the pillars of each [`ContributionLevel`].
+    pub levels: [Color; 5],
+    pub logo: Color,
+    /// Colour of the user handle, the date range and the other labels.
+    pub text: Color,
+}
+
+impl Default for Palette {
+    fn default() -> Self {
+        Palette {
+            base: Color([0x24, 0x29, 0x2f]),
+            pillars: Color([0x40, 0xc4, 0x63]),
+            levels: [
+                Color([0xeb, 0xed, 0xf0]),
+                Color([0x9b, 0xe9, 0xa8]),
+                Color([0x40, 0xc4, 0x63]),
+                Color([0x30, 0xa1, 0x4e]),
+                Color([0x21, 0x6e, 0x39]),
+            ],
+            logo: Color([0xff, 0xff, 0xff]),
+            text: Color([0xff, 0xff, 0xff]),
+        }
+    }
+}
+
+/// Build the trophy of [`render_mesh`] as separate parts, the base and
+/// the pillars, coloured with `palette` to be printed in several colours.
+/// If `by_level` is set, pillars are split in a part per contribution
+/// level, see [`Activity::as_level_matrix`].
+/// The logo and the texts are only built by OpenSCAD, see
+/// [`openscad_parts`].
+pub fn render_parts(
+    activity: &Activity,
+    shape: &TrophyShape,
+    render_options: &RenderOptions,
+    palette: &Palette,
+    by_level: bool,
+) -> Vec<Part> {
+    let values = activity_values(activity, render_options);
+    let heights = HeightMapping::fit(render_options.heights, &values).apply(&values);
+    let levels = activity.as_level_matrix(render_options.week_start);
+    let level_of = |week: usize, day: usize| -> ContributionLevel {
+        levels
+            .get(week)
+            .and_then(|days| days.get(day).copied().flatten())
+            .unwrap_or_default()
+    };
+
+    let (base, pillars) = if by_level {
+        trophy_part_meshes(&heights, shape, |week, day| Some(level_of(week, day)))
+    } else {
+        trophy_part_meshes(&heights, shape, |_, _| None)
+    };
+
+    let mut parts = vec![Part {
+        name: "base".to_string(),
+        color: palette.base,
+        mesh: base,
+    }];
+    parts.extend(pillars.into_iter().map(|(level, mesh)| match level {
+        Some(level) => Part {
+            name: format!("pillars level {}", level.value()),
+            color: palette.levels[level.value() as usize],
+            mesh,
+        },
+        None => Part {
+            name: "pillars".to_string(),
+            color: palette.pillars,
+            mesh,
+        },
+    }));
+    parts
+}
+
+/// Part of the trophy of [`render_openscad`] exported on its own by
+/// OpenSCAD, e.g: with [`OpenScad::export_meshes`](crate::openscad::runner::OpenScad::export_meshes).
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScadPart {
+    pub name: String,
+    pub color: Color,
+    /// OpenSCAD variables and expressions selecting the part, overriding
+    /// those of `trophy.scad` and of the program.
+    pub variables: Vec<(String, String)>,
+}
+
+/// Split the trophy of [`render_openscad`] in the parts of
+/// [`render_parts`] plus the logo and the texts, selected with the `part`
+/// variable of `trophy.scad`. Pillars of each contribution level, if
+/// `by_level` is set, are selected by overriding `rawActivity` with the
+/// heights of that level only.
+pub fn openscad_parts(
+    activity: &Activity,
+    render_options: &RenderOptions,
+    palette: &Palette,
+    by_level: bool,
+) -> Vec<ScadPart> {
+    let values = activity_values(activity, render_options);
+    let heights = HeightMapping::fit(render_options.heights, &values).apply(&values);
+    let levels = activity.as_level_matrix(render_options.week_start);
+    let group_of = |week: usize, day: usize| -> Option<ContributionLevel> {
+        by_level.then(|| {
+            levels
+                .get(week)
+                .and_then(|days| days.get(day).copied().flatten())
+                .unwrap_or_default()
+        })
+    };
+    let part = |name: &str, color: Color| ScadPart {
+        name: name.to_string(),
+        color,
+        variables: vec![("part".to_string(), name.to_scad())],
+    };
+
+    // Groups of pillars, as in `trophy_part_meshes`, OpenSCAD failing to
+    // export parts without pillars
+    let mut groups: Vec<Option<ContributionLevel>> = Vec::new();
+    for (week, days) in heights.iter().enumerate() {
+        for (day, height) in days.iter().enumerate() {
+            if height.is_some_and(|height| height > 0.0) {
+                groups.push(group_of(week, day));
+            }
+        }
+    }
+    groups.sort();
+    groups.dedup();
+
+    let mut parts = vec![part("base", palette.base)];
+    parts.extend(groups.into_iter().map(|group| match group {
+        Some(level) => {
+            let level_heights: Vec<Vec<Option<f64>>> = heights
+                .iter()
+                .enumerate()
+                .map(|(week, days)| {
+                    days.iter()
+                        .enumerate()
+                        .map(|(day, height)| height.filter(|_| group_of(week, day) == group))
+                        .collect()
+                })
+                .collect();
+            let mut level_part = part("pillars", palette.levels[level.value() as usize]);
+            level_part.name = format!("pillars level {}", level.value());
+            level_part
+                .variables
+                .push(("rawActivity".to_string(), level_heights.to_scad()));
+            level_part
+        }
+        None => part("pillars", palette.pillars),
+    }));
+    parts.push(part("logo", palette.logo));
+    parts.push(part("text", palette.text));
+    parts
+}
+
+/// Represent two activities, e.g: of two users or of the same user in
+/// consecutive years, as an OpenSCAD program for a comparison trophy.
+/// Their matrices are written to `leftActivity` and `rightActivity`,
+/// labelled by `leftActivityTxt` and `rightActivityTxt`, with heights
+/// scaled relative to both activities. Statistics and totals, if
+/// requested, are prefixed by `left` and `right`, e.g:
+/// `leftTotalContributions`.
+pub fn render_comparison_openscad(
+    left: &Activity,
+    left_label: String,
+    right: &Activity,
+    right_label: String,
+    layout: ComparisonLayout,
+    maybe_static_code: Option<String>,
+    render_options: &RenderOptions,
+) -> String {
+    let left_values = activity_values(left, render_options);
+    let right_values = activity_values(right, render_options);
+    let all_values: Vec<Vec<Option<u32>>> = left_values
+        .iter()
+        .chain(right_values.iter())
+        .cloned()
+        .collect();
+    let heights = HeightMapping::fit(render_options.heights, &all_values);
+
+    let mut variables = vec![("comparisonLayout".to_string(), layout.name().to_scad())];
+    for (prefix, activity) in [("left", left), ("right", right)] {
+        variables.extend(
+            summary_variables(activity, render_options)
+                .into_iter()
+                .map(|(name, expression)| (prefixed_variable(prefix, &name), expression)),
+        );
+    }
+
+    let span = if left.date_range == right.date_range {
+        span_text(left)
+    } else {
+        format!("{} | {}", span_text(left), span_text(right))
+    };
+
+    generate_datasets_source(
+        vec![
+            Dataset::labelled(
+                "leftActivity".to_string(),
+                left_label,
+                heights.apply(&left_values),
+            ),
+            Dataset::labelled(
+                "rightActivity".to_string(),
+                right_label,
+                heights.apply(&right_values),
+            ),
+        ],
+        span,
+        variables,
+        maybe_static_code,
+    )
+}
+
+/// Camel case `name` prefixed by `prefix`, e.g: `leftTotalContributions`.
+fn prefixed_variable(prefix: &str, name: &str) -> String {
+    let mut chars = name.chars();
+    match chars.next() {
+        Some(first) => format!("{}{}{}", prefix, first.to_ascii_uppercase(), chars.as_str()),
+        None => prefix.to_string(),
+    }
+}
+
+/// Represent activity spanning several years as an OpenSCAD program for
+/// a layered trophy, each calendar year being a tier labelled with the
+/// year. Matrices of the years are written, oldest first, to the
+/// `layerActivity` array and their labels to `layerTxt`. Heights of every
+/// year are scaled relative to the overall activity.
+pub fn render_layers_openscad(
+    activity: &Activity,
+    user_handle: String,
+    maybe_static_code: Option<String>,
+    render_options: &RenderOptions,
+) -> String {
+    let years: Vec<(String, Vec<Vec<Option<u32>>>)> = activity
+        .split_by_year()
+        .iter()
+        .map(|year| {
+            (
+                year.date_range.0.year().to_string(),
+                activity_values(year, render_options),
+            )
+        })
+        .collect();
+    let all_values: Vec<Vec<Option<u32>>> = years
+        .iter()
+        .flat_map(|(_, values)| values.iter().cloned())
+        .collect();
+    let heights = HeightMapping::fit(render_options.heights, &all_values);
+
+    let variables = summary_variables(activity, render_options);
+
+    generate_layers_source(
+        user_handle,
+        span_text(activity),
+        years
+            .into_iter()
+            .map(|(label, values)| (label, heights.apply(&values)))
+            .collect(),
+        variables,
+        maybe_static_code,
+    )
+}
+
 pub async fn generate_openscad(
+    client: &GitHubClient,
     user_handle: String,
     start_date: NaiveDate,
     end_date: NaiveDate,
-    maybe_token: Option<String>,
     maybe_static_code: Option<String>,
-) -> Result<String, Box<dyn std::error::Error>> {
-    let result = activity::get_activity(&user_handle, (start_date, end_date), maybe_token).await?;
+    options: &ActivityOptions,
+    render_options: &RenderOptions,
+) -> Result<String, GitHubError> {
+    let result =
+        activity::get_activity(client, &user_handle, (start_date, end_date), options).await?;
 
-    let result_as_simple_matrix = result.as_matrix();
-    let result_as_scad_data = generate_data_source(
+    Ok(render_openscad(
+        &result,
+        user_handle,
+        maybe_static_code,
+        render_options,
+    ))
+}
+
+/// Download the activity of `user_handle` and represent it as a layered
+/// trophy with a tier per calendar year, see [`render_layers_openscad`].
+pub async fn generate_layers_openscad(
+    client: &GitHubClient,
+    user_handle: String,
+    start_date: NaiveDate,
+    end_date: NaiveDate,
+    maybe_static_code: Option<String>,
+    options: &ActivityOptions,
+    render_options: &RenderOptions,
+) -> Result<String, GitHubError> {
+    let result =
+        activity::get_activity(client, &user_handle, (start_date, end_date), options).await?;
+
+    Ok(render_layers_openscad(
+        &result,
         user_handle,
-        format!(
-            "{}/{} - {}/{}",
-            start_date.year(),
-            start_date.month(),
-            end_date.year(),
-            end_date.month()
-        ),
-        result_as_simple_matrix,
         maybe_static_code,
-    );
+        render_options,
+    ))
+}
+
+/// Download and merge the activity of the members of `team`.
+/// Fails if any of the members activity can not be downloaded.
+pub async fn get_team_activity(
+    client: &GitHubClient,
+    team: &Team,
+    start_date: NaiveDate,
+    end_date: NaiveDate,
+    options: &ActivityOptions,
+) -> Result<Activity, GitHubError> {
+    let activities = activity::get_activities(
+        client,
+        &team.members,
+        (start_date, end_date),
+        options,
+        team.parallelism,
+    )
+    .await?;
+    Ok(Activity::merge(&activities, team.merge)
+        .unwrap_or_else(|| Activity::from_daily_contributions((start_date, end_date), vec![])))
+}
+
+/// Download and merge the activity of the members of `team` into a
+/// single trophy.
+/// Fails if any of the members activity can not be downloaded.
+pub async fn generate_team_openscad(
+    client: &GitHubClient,
+    team: &Team,
+    start_date: NaiveDate,
+    end_date: NaiveDate,
+    maybe_static_code: Option<String>,
+    options: &ActivityOptions,
+    render_options: &RenderOptions,
+) -> Result<String, GitHubError> {
+    let merged = get_team_activity(client, team, start_date, end_date, options).await?;
+
+    Ok(render_team_openscad(
+        &merged,
+        team,
+        maybe_static_code,
+        render_options,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2024, month, day).unwrap()
+    }
+
+    #[test]
+    fn test_render_comparison_openscad() {
+        // Monday 2024-01-01 to Sunday 2024-01-07
+        let range = (date(1, 1), date(1, 7));
+        let left = Activity::from_daily_contributions(range, vec![(date(1, 1), 5)]);
+        let right = Activity::from_daily_contributions(range, vec![(date(1, 2), 10)]);
+        let render_options = RenderOptions {
+            heights: HeightOptions::new(crate::heights::HeightScale::Linear, 0.0, 10.0).unwrap(),
+            stats: true,
+            ..Default::default()
+        };
+        let result = render_comparison_openscad(
+            &left,
+            "alice".to_string(),
+            &right,
+            "bob".to_string(),
+            ComparisonLayout::Interleaved,
+            None,
+            &render_options,
+        );
+
+        // Heights are scaled relative to the busiest day of both activities
+        assert!(result.contains("leftActivity = [\n    [5, 0, 0, 0, 0, 0, 0],\n];"));
+        assert!(result.contains("rightActivity = [\n    [0, 10, 0, 0, 0, 0, 0],\n];"));
+        assert!(result.contains("leftActivityTxt = \"alice\";\nrightActivityTxt = \"bob\";\n"));
+        assert!(result.contains("spanTxt = \"2024/1 - 2024/1\";\n"));
+        assert!(result.contains("comparisonLayout = \"interleaved\";\n"));
+        assert!(result.contains("leftTotalContributions = 5;\n"));
+        assert!(result.contains("rightTotalContributions = 10;\n"));
+    }
+
+    #[test]
+    fn test_render_team_openscad_escapes_names() {
+        let range = (date(1, 1), date(1, 7));
+        let activity = Activity::from_daily_contributions(range, vec![(date(1, 1), 5)]);
+        let team = Team::new(
+            "The \"core\" team".to_string(),
+            vec!["alice".to_string(), "b\\ob".to_string()],
+        );
+        let result = render_team_openscad(&activity, &team, None, &RenderOptions::default());
+
+        assert!(result.contains(r#"ghHandleTxt = "The \"core\" team";"#));
+        assert!(result.contains(r#"teamMembers = ["alice", "b\\ob"];"#));
+        assert!(result.contains(r#"membersTxt = "alice, b\\ob";"#));
+    }
+
+    #[test]
+    fn test_render_layers_openscad() {
+        let activity = Activity::from_daily_contributions(
+            (
+                NaiveDate::from_ymd_opt(2023, 12, 25).unwrap(),
+                NaiveDate::from_ymd_opt(2024, 1, 7).unwrap(),
+            ),
+            vec![
+                (NaiveDate::from_ymd_opt(2023, 12, 25).unwrap(), 10),
+                (date(1, 2), 5),
+            ],
+        );
+        let render_options = RenderOptions {
+            heights: HeightOptions::new(crate::heights::HeightScale::Linear, 0.0, 10.0).unwrap(),
+            ..Default::default()
+        };
+        let result = render_layers_openscad(&activity, "layers".to_string(), None, &render_options);
+
+        assert!(result.contains(concat!(
+            "layerActivity = [\n",
+            "    [\n        [10, 0, 0, 0, 0, 0, 0],\n    ],\n",
+            "    [\n        [0, 5, 0, 0, 0, 0, 0],\n    ],\n",
+            "];"
+        )));
+        assert!(result.contains("layerTxt = [\"2023\", \"2024\"];\n"));
+        assert!(result.contains("spanTxt = \"2023/12 - 2024/1\";\n"));
+    }
+
+    #[test]
+    fn test_totals_variables() {
+        let variables = totals_variables(Some(ContributionTotals {
+            total_contributions: 20,
+            restricted_contributions: 12,
+            has_any_restricted_contributions: true,
+        }));
+        assert_eq!(
+            variables,
+            vec![
+                ("calendarContributions".to_string(), "20".to_string()),
+                ("restrictedContributions".to_string(), "12".to_string()),
+                ("hasRestrictedContributions".to_string(), "true".to_string()),
+                (
+                    "privateTxt".to_string(),
+                    "\"plus 12 private contributions\"".to_string()
+                ),
+            ]
+        );
+
+        let missing = totals_variables(None);
+        assert_eq!(missing[0].1, "undef");
+        assert_eq!(missing[3].1, "\"\"");
+    }
+
+    #[test]
+    fn test_render_parts() {
+        // Monday 2024-01-01 to Sunday 2024-01-07
+        let activity = Activity::from_daily_contributions(
+            (date(1, 1), date(1, 7)),
+            vec![(date(1, 1), 1), (date(1, 2), 9)],
+        );
+        let palette = Palette::default();
+        let render_options = RenderOptions::default();
+
+        let parts = render_parts(
+            &activity,
+            &TrophyShape::default(),
+            &render_options,
+            &palette,
+            false,
+        );
+        let names: Vec<&str> = parts.iter().map(|part| part.name.as_str()).collect();
+        assert_eq!(names, vec!["base", "pillars"]);
+        assert_eq!(parts[1].color, palette.pillars);
+
+        let parts = render_parts(
+            &activity,
+            &TrophyShape::default(),
+            &render_options,
+            &palette,
+            true,
+        );
+        let names: Vec<&str> = parts.iter().map(|part| part.name.as_str()).collect();
+        assert_eq!(
+            names,
+            vec![
+                "base",
+                "pillars level 0",
+                "pillars level 1",
+                "pillars level 4"
+            ]
+        );
+        assert_eq!(parts[3].color, palette.levels[4]);
+    }
+
+    #[test]
+    fn test_openscad_parts() {
+        // Monday 2024-01-01 to Sunday 2024-01-07
+        let activity = Activity::from_daily_contributions(
+            (date(1, 1), date(1, 7)),
+            vec![(date(1, 1), 1), (date(1, 2), 9)],
+        );
+        let palette = Palette::default();
+        let render_options = RenderOptions {
+            heights: HeightOptions::new(crate::heights::HeightScale::Linear, 0.0, 9.0).unwrap(),
+            ..Default::default()
+        };
+        let part_variable = |part: &ScadPart| part.variables[0].clone();
+
+        let parts = openscad_parts(&activity, &render_options, &palette, false);
+        let names: Vec<&str> = parts.iter().map(|part| part.name.as_str()).collect();
+        assert_eq!(names, vec!["base", "pillars", "logo", "text"]);
+        assert_eq!(
+            part_variable(&parts[3]),
+            ("part".to_string(), "\"text\"".to_string())
+        );
+        assert_eq!(parts[2].color, palette.logo);
 
-    Ok(result_as_scad_data)
+        // Days without contributions get no pillar with a minimum height of 0
+        let parts = openscad_parts(&activity, &render_options, &palette, true);
+        let names: Vec<&str> = parts.iter().map(|part| part.name.as_str()).collect();
+        assert_eq!(
+            names,
+            vec!["base", "pillars level 1", "pillars level 4", "logo", "text"]
+        );
+        assert_eq!(parts[2].color, palette.levels[4]);
+        assert_eq!(
+            parts[2].variables,
+            vec![
+                ("part".to_string(), "\"pillars\"".to_string()),
+                (
+                    "rawActivity".to_string(),
+                    "[[undef, 9, undef, undef, undef, undef, undef]]".to_string()
+                ),
+            ]
+        );
+    }
 }
@@ -1 +1,5 @@
 pub mod activity;
+pub mod cache;
+pub mod client;
+pub mod error;
+pub mod organization;
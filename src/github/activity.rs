@@ -1,12 +1,22 @@
-use chrono::{Datelike, NaiveDate, Weekday};
-use serde::{Deserialize, Serialize, Serializer};
+use chrono::{DateTime, Datelike, NaiveDate, Utc, Weekday};
+use futures::{StreamExt, TryStreamExt};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use std::collections::HashMap;
 
+use crate::github::cache::ActivityCache;
+use crate::github::client::GitHubClient;
+use crate::github::error::GitHubError;
+use crate::github::organization::fetch_organization_id;
+
 type UserName<'a> = &'a str;
 
-type DateRange = (NaiveDate, NaiveDate);
+pub(crate) type DateRange = (NaiveDate, NaiveDate);
+
+/// Contributions grouped by ISO week and, within each week, by day of the week.
+pub type WeeklyContributions = HashMap<YearWeek, HashMap<Weekday, u32>>;
 
-#[derive(Debug, Hash, Eq, PartialEq)]
+#[derive(Debug, Clone, Hash, Eq, PartialEq)]
 pub struct YearWeek {
     pub year: usize,
     pub week: usize,
@@ -21,8 +31,264 @@ impl Serialize for YearWeek {
     }
 }
 
+impl<'de> Deserialize<'de> for YearWeek {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let text = String::deserialize(deserializer)?;
+        text.split_once("-W")
+            .and_then(|(year, week)| {
+                Some(YearWeek {
+                    year: year.parse().ok()?,
+                    week: week.parse().ok()?,
+                })
+            })
+            .ok_or_else(|| {
+                serde::de::Error::custom(format!("invalid ISO week `{}`, expected YYYY-Www", text))
+            })
+    }
+}
+
+/// Type of contribution as classified by GitHub in the user
+/// `contributionsCollection`.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Hash, Eq, PartialEq)]
+pub enum ContributionKind {
+    Commit,
+    PullRequest,
+    Issue,
+    PullRequestReview,
+    Repository,
+}
+
+impl ContributionKind {
+    pub const ALL: [ContributionKind; 5] = [
+        ContributionKind::Commit,
+        ContributionKind::PullRequest,
+        ContributionKind::Issue,
+        ContributionKind::PullRequestReview,
+        ContributionKind::Repository,
+    ];
+
+    /// Name of the `contributionsCollection` field listing this kind
+    /// of contributions.
+    fn collection_field(&self) -> &'static str {
+        match self {
+            ContributionKind::Commit => "commitContributionsByRepository",
+            ContributionKind::PullRequest => "pullRequestContributions",
+            ContributionKind::Issue => "issueContributions",
+            ContributionKind::PullRequestReview => "pullRequestReviewContributions",
+            ContributionKind::Repository => "repositoryContributions",
+        }
+    }
+
+    /// Selection of the repository of each contribution node of this kind.
+    /// Pull requests and issues are aliased as `subject`.
+    fn repository_selection(&self) -> &'static str {
+        match self {
+            ContributionKind::PullRequest => {
+                "subject: pullRequest { repository { nameWithOwner } }"
+            }
+            ContributionKind::Issue => "subject: issue { repository { nameWithOwner } }",
+            _ => "repository { nameWithOwner }",
+        }
+    }
+}
+
+/// Filters restricting the contributions taken into account by
+/// [`get_activity`]. Repositories are identified by their `owner/name`
+/// and organizations by their login, both compared case insensitively.
+/// Empty include lists do not restrict anything.
+#[derive(Debug, Clone, Default)]
+pub struct ActivityFilters {
+    pub include_repositories: Vec<String>,
+    pub exclude_repositories: Vec<String>,
+    pub include_organizations: Vec<String>,
+    pub exclude_organizations: Vec<String>,
+}
+
+impl ActivityFilters {
+    /// Whether the filters can only be applied by looking at the repository
+    /// of each contribution. Including organizations alone is solved by
+    /// GitHub with the `organizationID` argument of `contributionsCollection`.
+    fn requires_repositories(&self) -> bool {
+        !self.include_repositories.is_empty()
+            || !self.exclude_repositories.is_empty()
+            || !self.exclude_organizations.is_empty()
+    }
+
+    /// Check whether contributions to the repository `name_with_owner`
+    /// (e.g: `pfcoperez/gh-trophy`) pass the filters.
+    pub fn accepts_repository(&self, name_with_owner: &str) -> bool {
+        let owner = name_with_owner.split('/').next().unwrap_or_default();
+        let contains =
+            |names: &[String], name: &str| names.iter().any(|n| n.eq_ignore_ascii_case(name));
+
+        (self.include_repositories.is_empty()
+            || contains(&self.include_repositories, name_with_owner))
+            && !contains(&self.exclude_repositories, name_with_owner)
+            && (self.include_organizations.is_empty()
+                || contains(&self.include_organizations, owner))
+            && !contains(&self.exclude_organizations, owner)
+    }
+}
+
+/// Intensity of the contributions made on a day relative to the rest of
+/// the activity, as shown by GitHub in the contribution calendar.
+/// Variant names match the `ContributionLevel` GraphQL enum.
+#[derive(
+    Serialize, Deserialize, Debug, Clone, Copy, Default, Hash, Eq, PartialEq, Ord, PartialOrd,
+)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ContributionLevel {
+    #[default]
+    None,
+    FirstQuartile,
+    SecondQuartile,
+    ThirdQuartile,
+    FourthQuartile,
+}
+
+impl ContributionLevel {
+    /// Level as a number from 0, no contributions, to 4.
+    pub fn value(self) -> u32 {
+        self as u32
+    }
+}
+
+/// Boundaries of the first three quartiles of the number of contributions
+/// on days with at least one contribution.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Quartiles([u32; 3]);
+
+impl Quartiles {
+    fn of(counts: impl IntoIterator<Item = u32>) -> Self {
+        let mut counts: Vec<u32> = counts.into_iter().filter(|count| *count > 0).collect();
+        counts.sort_unstable();
+        let quantile = |quarters: usize| {
+            counts
+                .get((counts.len().saturating_sub(1)) * quarters / 4)
+                .copied()
+                .unwrap_or(0)
+        };
+        Quartiles([quantile(1), quantile(2), quantile(3)])
+    }
+
+    fn level(&self, count: u32) -> ContributionLevel {
+        let [first, second, third] = self.0;
+        match count {
+            0 => ContributionLevel::None,
+            count if count <= first => ContributionLevel::FirstQuartile,
+            count if count <= second => ContributionLevel::SecondQuartile,
+            count if count <= third => ContributionLevel::ThirdQuartile,
+            _ => ContributionLevel::FourthQuartile,
+        }
+    }
+}
+
+/// First day of the week in matrix representations of the activity.
+/// GitHub profiles show weeks starting on Sunday while ISO weeks,
+/// used to group contributions, start on Monday.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum WeekStart {
+    #[default]
+    Monday,
+    Sunday,
+}
+
+impl WeekStart {
+    fn weekday(&self) -> Weekday {
+        match self {
+            WeekStart::Monday => Weekday::Mon,
+            WeekStart::Sunday => Weekday::Sun,
+        }
+    }
+
+    /// Position of `weekday` in weeks starting on this day.
+    pub fn day_index(&self, weekday: Weekday) -> usize {
+        weekday.days_since(self.weekday()) as usize
+    }
+
+    /// First day of the week `date` belongs to.
+    pub fn first_day_of_week(&self, date: NaiveDate) -> NaiveDate {
+        date - chrono::Duration::days(self.day_index(date.weekday()) as i64)
+    }
+}
+
+/// How [`Activity::merge`] combines the contributions made on the same
+/// day in several activities.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum MergeMode {
+    /// Total number of contributions.
+    #[default]
+    Sum,
+    /// Highest number of contributions in a single activity.
+    Max,
+    /// Number of activities with contributions, e.g: team members
+    /// active on that day.
+    ActiveMembers,
+}
+
+impl MergeMode {
+    fn combine(self, counts: impl Iterator<Item = u32>) -> u32 {
+        match self {
+            MergeMode::Sum => counts.sum(),
+            MergeMode::Max => counts.max().unwrap_or(0),
+            MergeMode::ActiveMembers => counts.filter(|count| *count > 0).count() as u32,
+        }
+    }
+}
+
+/// Options controlling which data [`get_activity`] downloads.
+#[derive(Debug, Clone, Default)]
+pub struct ActivityOptions {
+    /// Also download contributions broken down by [`ContributionKind`].
+    /// This requires several extra queries per year of activity.
+    /// GitHub only lists the commits to 100 repositories every 100 days,
+    /// so commits of users contributing to more repositories are missing.
+    pub by_kind: bool,
+    /// Only take into account contributions passing these filters.
+    pub filters: ActivityFilters,
+    /// Reuse, and store, downloaded contributions in this cache.
+    pub cache: Option<ActivityCache>,
+    /// Keep the contribution levels computed by GitHub instead of
+    /// computing them from the downloaded contributions. GitHub computes
+    /// levels for each year long window so, in longer ranges, levels of
+    /// different years are not comparable.
+    pub github_levels: bool,
+    /// Node ids of organizations by login, e.g: resolved once for every
+    /// member of an organization. Included organizations missing here are
+    /// looked up before downloading contributions to them.
+    pub organization_ids: HashMap<String, String>,
+}
+
+/// Totals GitHub reports for the contributions of a user, including
+/// those made to private repositories the client has no access to.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ContributionTotals {
+    /// Contributions in the contribution calendar, restricted ones are
+    /// only included if the user shows private contributions on their
+    /// profile.
+    pub total_contributions: u32,
+    /// Contributions to private repositories the client can not see.
+    pub restricted_contributions: u32,
+    pub has_any_restricted_contributions: bool,
+}
+
+impl ContributionTotals {
+    fn add(self, other: ContributionTotals) -> Self {
+        ContributionTotals {
+            total_contributions: self.total_contributions + other.total_contributions,
+            restricted_contributions: self.restricted_contributions
+                + other.restricted_contributions,
+            has_any_restricted_contributions: self.has_any_restricted_contributions
+                || other.has_any_restricted_contributions,
+        }
+    }
+}
+
 /// Data structure representing the GitHub user activity
-#[derive(Serialize, Debug)]
+#[derive(Serialize, Deserialize, Debug)]
 pub struct Activity {
     /// Date range of the represented activity period
     pub date_range: DateRange,
@@ -30,72 +296,351 @@ pub struct Activity {
     /// year to weeks.
     /// Weeks are represented as a map from the day of the way (e.g: 0 -> Monday)
     /// to the number of contributions on that day.
-    pub contributions: HashMap<YearWeek, HashMap<Weekday, u32>>,
+    pub contributions: WeeklyContributions,
+    /// Contributions split by kind, with the same layout as `contributions`.
+    /// Empty unless requested with [`ActivityOptions::by_kind`].
+    pub contributions_by_kind: HashMap<ContributionKind, WeeklyContributions>,
+    /// Contribution levels of the days with contributions as computed by
+    /// GitHub, only set if requested with [`ActivityOptions::github_levels`].
+    #[serde(default)]
+    pub github_levels: Option<HashMap<NaiveDate, ContributionLevel>>,
+    /// Totals reported by GitHub for the whole date range. Missing if the
+    /// contribution calendar was not downloaded, e.g: when filtering by
+    /// repository, or if only part of the range was downloaded again
+    /// from a cache.
+    #[serde(default)]
+    pub totals: Option<ContributionTotals>,
+    /// Windows in which GitHub only listed the commits to 100 repositories,
+    /// so commits to any other repository are missing from the
+    /// contributions by kind, and from `contributions` when filtering by
+    /// repository.
+    #[serde(default)]
+    pub truncated_commit_windows: Vec<DateRange>,
 }
 
 impl Activity {
+    /// Number of weeks, Monday to Sunday, with at least one day in the
+    /// activity date range.
     pub fn number_of_weeks(&self) -> usize {
-        ((self.date_range.1 - self.date_range.0).num_days() as f32 / 7.0).ceil() as usize
+        number_of_weeks(self.date_range)
     }
 
-    /// Obtain a simplified representation of the activity data.
-    /// This is a 2D matrix where rows are weeks and columns days of
-    /// the week with Monday at index 0.
-    pub fn as_matrix(&self) -> Vec<Vec<u32>> {
-        let mut matrix: Vec<Vec<u32>> = vec![vec![0; 7]; self.number_of_weeks()];
-        for date in self.date_range.0.iter_weeks().take(self.number_of_weeks()) {
-            let year_week = get_year_week(date);
-            if let Some(week_entry) = self.contributions.get(&year_week) {
-                for (weekday, count) in week_entry {
-                    let week_index = year_week.week - 1;
-                    let day_index = weekday.num_days_from_monday() as usize;
-                    matrix[week_index][day_index] = count.clone();
+    /// Number of contributions made on `date`.
+    pub fn contributions_on(&self, date: NaiveDate) -> u32 {
+        weekly_contributions_on(&self.contributions, date)
+    }
+
+    /// Combine several activities, e.g: of the members of a team, into one
+    /// spanning all their date ranges. Contributions by kind are only kept
+    /// for the kinds every activity has and totals are only added up, if
+    /// every activity has them, with [`MergeMode::Sum`]. Returns `None` if
+    /// there are no activities.
+    pub fn merge(activities: &[Activity], mode: MergeMode) -> Option<Activity> {
+        let start = activities
+            .iter()
+            .map(|activity| activity.date_range.0)
+            .min()?;
+        let end = activities
+            .iter()
+            .map(|activity| activity.date_range.1)
+            .max()?;
+        let date_range = (start, end);
+
+        let merge_weeks = |all_weeks: Vec<&WeeklyContributions>| {
+            let mut merged = empty_weeks(date_range);
+            for date in start.iter_days().take_while(|date| *date <= end) {
+                let count = mode.combine(
+                    all_weeks
+                        .iter()
+                        .map(|weeks| weekly_contributions_on(weeks, date)),
+                );
+                if count > 0 {
+                    add_contributions(&mut merged, date, count);
                 }
             }
+            merged
+        };
+
+        let contributions = merge_weeks(
+            activities
+                .iter()
+                .map(|activity| &activity.contributions)
+                .collect(),
+        );
+        let contributions_by_kind = ContributionKind::ALL
+            .into_iter()
+            .filter_map(|kind| {
+                let all_weeks: Option<Vec<&WeeklyContributions>> = activities
+                    .iter()
+                    .map(|activity| activity.contributions_by_kind.get(&kind))
+                    .collect();
+                all_weeks.map(|all_weeks| (kind, merge_weeks(all_weeks)))
+            })
+            .collect();
+
+        let totals = match mode {
+            MergeMode::Sum => activities
+                .iter()
+                .map(|activity| activity.totals)
+                .collect::<Option<Vec<ContributionTotals>>>()
+                .map(|all_totals| {
+                    all_totals
+                        .into_iter()
+                        .fold(ContributionTotals::default(), ContributionTotals::add)
+                }),
+            _ => None,
+        };
+
+        let mut truncated_commit_windows: Vec<DateRange> = activities
+            .iter()
+            .flat_map(|activity| activity.truncated_commit_windows.iter().copied())
+            .collect();
+        truncated_commit_windows.sort();
+        truncated_commit_windows.dedup();
+
+        Some(Activity {
+            date_range,
+            contributions,
+            contributions_by_kind,
+            github_levels: None,
+            totals,
+            truncated_commit_windows,
+        })
+    }
+
+    /// Part of the activity within `date_range`, which is clamped to the
+    /// activity date range. Totals are only kept if the whole activity is
+    /// within `date_range`. Returns `None` if they do not overlap.
+    pub fn slice(&self, date_range: DateRange) -> Option<Activity> {
+        let start = date_range.0.max(self.date_range.0);
+        let end = date_range.1.min(self.date_range.1);
+        if start > end {
+            return None;
         }
-        return matrix;
+        let date_range = (start, end);
+
+        let slice_weeks = |weeks: &WeeklyContributions| {
+            let mut sliced = empty_weeks(date_range);
+            for date in start.iter_days().take_while(|date| *date <= end) {
+                let count = weekly_contributions_on(weeks, date);
+                if count > 0 {
+                    add_contributions(&mut sliced, date, count);
+                }
+            }
+            sliced
+        };
+
+        Some(Activity {
+            date_range,
+            contributions: slice_weeks(&self.contributions),
+            contributions_by_kind: self
+                .contributions_by_kind
+                .iter()
+                .map(|(kind, weeks)| (*kind, slice_weeks(weeks)))
+                .collect(),
+            github_levels: self.github_levels.as_ref().map(|levels| {
+                levels
+                    .iter()
+                    .filter(|(date, _)| **date >= start && **date <= end)
+                    .map(|(date, level)| (*date, *level))
+                    .collect()
+            }),
+            totals: self.totals.filter(|_| date_range == self.date_range),
+            truncated_commit_windows: clip_windows(&self.truncated_commit_windows, date_range),
+        })
     }
-}
 
-// GraphQL request and response structures
-#[derive(Serialize, Debug)]
-struct GraphQLRequest {
-    query: String,
-    variables: GraphQLVariables,
+    /// Split the activity by calendar year, oldest year first. The first
+    /// and last years only span the days in the activity date range.
+    pub fn split_by_year(&self) -> Vec<Activity> {
+        (self.date_range.0.year()..=self.date_range.1.year())
+            .filter_map(|year| {
+                self.slice((
+                    NaiveDate::from_ymd_opt(year, 1, 1)?,
+                    NaiveDate::from_ymd_opt(year, 12, 31)?,
+                ))
+            })
+            .collect()
+    }
+
+    /// Build an activity from the number of contributions made each day.
+    pub fn from_daily_contributions(
+        date_range: DateRange,
+        days: impl IntoIterator<Item = (NaiveDate, u32)>,
+    ) -> Self {
+        let raw_contributions = RawContributions {
+            calendar: days
+                .into_iter()
+                .filter(|(date, count)| {
+                    *count > 0 && *date >= date_range.0 && *date <= date_range.1
+                })
+                .collect(),
+            ..Default::default()
+        };
+        build_activity(date_range, raw_contributions, &ActivityOptions::default())
+    }
+
+    /// Obtain a simplified representation of the activity data.
+    /// This is a 2D matrix where rows are weeks and columns days of
+    /// the week with Monday at index 0. Days of the first and last weeks
+    /// out of the activity date range are `None`, unlike days without
+    /// contributions which are `Some(0)`.
+    pub fn as_matrix(&self) -> Vec<Vec<Option<u32>>> {
+        self.as_calendar_matrix(WeekStart::Monday)
+    }
+
+    /// Same as [`Activity::as_matrix`] but only counting contributions of
+    /// the given kind. Returns `None` if the activity was not downloaded
+    /// broken down by kind.
+    pub fn as_matrix_of_kind(&self, kind: ContributionKind) -> Option<Vec<Vec<Option<u32>>>> {
+        self.as_calendar_matrix_of_kind(kind, WeekStart::Monday)
+    }
+
+    /// Lay out the activity as GitHub does in the contribution calendar of
+    /// user profiles: rows are weeks beginning on `week_start` and columns
+    /// days of the week. Days of the first and last weeks out of the activity
+    /// date range are `None`.
+    pub fn as_calendar_matrix(&self, week_start: WeekStart) -> Vec<Vec<Option<u32>>> {
+        self.weeks_as_calendar(&self.contributions, week_start)
+    }
+
+    /// Same as [`Activity::as_calendar_matrix`] but only counting
+    /// contributions of the given kind. Returns `None` if the activity was
+    /// not downloaded broken down by kind.
+    pub fn as_calendar_matrix_of_kind(
+        &self,
+        kind: ContributionKind,
+        week_start: WeekStart,
+    ) -> Option<Vec<Vec<Option<u32>>>> {
+        self.contributions_by_kind
+            .get(&kind)
+            .map(|weeks| self.weeks_as_calendar(weeks, week_start))
+    }
+
+    /// Contribution level of each day, laid out as in
+    /// [`Activity::as_calendar_matrix`].
+    /// Levels are those computed by GitHub if they were downloaded or,
+    /// otherwise, the quartile of the day contributions among the days
+    /// with contributions, like GitHub does.
+    pub fn as_level_matrix(&self, week_start: WeekStart) -> Vec<Vec<Option<ContributionLevel>>> {
+        match &self.github_levels {
+            Some(levels) => self.calendar_layout(week_start, |date| {
+                levels.get(&date).copied().unwrap_or_default()
+            }),
+            None => self.weeks_as_levels(&self.contributions, week_start),
+        }
+    }
+
+    /// Same as [`Activity::as_level_matrix`] but for contributions of the
+    /// given kind, always computing the levels from their quartiles.
+    /// Returns `None` if the activity was not downloaded broken down by kind.
+    pub fn as_level_matrix_of_kind(
+        &self,
+        kind: ContributionKind,
+        week_start: WeekStart,
+    ) -> Option<Vec<Vec<Option<ContributionLevel>>>> {
+        self.contributions_by_kind
+            .get(&kind)
+            .map(|weeks| self.weeks_as_levels(weeks, week_start))
+    }
+
+    fn weeks_as_levels(
+        &self,
+        weeks: &WeeklyContributions,
+        week_start: WeekStart,
+    ) -> Vec<Vec<Option<ContributionLevel>>> {
+        let counts = self.weeks_as_calendar(weeks, week_start);
+        let quartiles = Quartiles::of(counts.iter().flatten().flatten().copied());
+        counts
+            .into_iter()
+            .map(|week| {
+                week.into_iter()
+                    .map(|count| count.map(|count| quartiles.level(count)))
+                    .collect()
+            })
+            .collect()
+    }
+
+    fn weeks_as_calendar(
+        &self,
+        weeks: &WeeklyContributions,
+        week_start: WeekStart,
+    ) -> Vec<Vec<Option<u32>>> {
+        self.calendar_layout(week_start, |date| weekly_contributions_on(weeks, date))
+    }
+
+    /// Rows are counted from the week of the first day in the date range
+    /// so ranges can start on any day and span several years.
+    fn calendar_layout<T: Clone>(
+        &self,
+        week_start: WeekStart,
+        value_on: impl Fn(NaiveDate) -> T,
+    ) -> Vec<Vec<Option<T>>> {
+        let (start, end) = self.date_range;
+        let first_week_day = week_start.first_day_of_week(start);
+        let number_of_weeks =
+            ((week_start.first_day_of_week(end) - first_week_day).num_days() / 7 + 1) as usize;
+        let mut matrix: Vec<Vec<Option<T>>> = vec![vec![None; 7]; number_of_weeks];
+        for date in start.iter_days().take_while(|date| *date <= end) {
+            let week_index = ((date - first_week_day).num_days() / 7) as usize;
+            let day_index = week_start.day_index(date.weekday());
+            matrix[week_index][day_index] = Some(value_on(date));
+        }
+        matrix
+    }
 }
 
+// GraphQL request and response structures
 #[derive(Serialize, Debug)]
 struct GraphQLVariables {
     username: String,
     from: String,
     to: String,
+    #[serde(rename = "organizationID", skip_serializing_if = "Option::is_none")]
+    organization_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    after: Option<String>,
 }
 
 #[derive(Deserialize, Debug)]
-struct GraphQLResponse {
-    data: GraphQLData,
-}
-
-#[derive(Deserialize, Debug)]
-struct GraphQLData {
-    user: User,
+struct GraphQLData<T> {
+    user: Option<User<T>>,
 }
 
 #[derive(Deserialize, Debug)]
-struct User {
+struct User<T> {
     #[serde(rename = "contributionsCollection")]
-    contributions_collection: ContributionsCollection,
+    contributions_collection: T,
 }
 
 #[derive(Deserialize, Debug)]
 struct ContributionsCollection {
     #[serde(rename = "contributionCalendar")]
     contribution_calendar: ContributionCalendar,
+    #[serde(rename = "restrictedContributionsCount")]
+    restricted_contributions_count: u32,
+    #[serde(rename = "hasAnyRestrictedContributions")]
+    has_any_restricted_contributions: bool,
+}
+
+#[derive(Deserialize, Debug)]
+struct TotalsCollection {
+    #[serde(rename = "contributionCalendar")]
+    contribution_calendar: CalendarTotal,
+    #[serde(rename = "restrictedContributionsCount")]
+    restricted_contributions_count: u32,
+    #[serde(rename = "hasAnyRestrictedContributions")]
+    has_any_restricted_contributions: bool,
+}
+
+#[derive(Deserialize, Debug)]
+struct CalendarTotal {
+    #[serde(rename = "totalContributions")]
+    total_contributions: u32,
 }
 
 #[derive(Deserialize, Debug)]
 struct ContributionCalendar {
-    #[allow(dead_code)]
     #[serde(rename = "totalContributions")]
     total_contributions: u32,
     weeks: Vec<Week>,
@@ -112,6 +657,85 @@ struct ContributionDay {
     date: String,
     #[serde(rename = "contributionCount")]
     contribution_count: u32,
+    #[serde(rename = "contributionLevel")]
+    contribution_level: ContributionLevel,
+}
+
+/// Collection where the queried contributions connection is aliased as
+/// `contributions` so the same structure serves every [`ContributionKind`]
+/// but commits.
+#[derive(Deserialize, Debug)]
+struct KindContributionsCollection {
+    contributions: ContributionsConnection,
+}
+
+#[derive(Deserialize, Debug)]
+struct ContributionsConnection {
+    #[serde(rename = "pageInfo")]
+    page_info: PageInfo,
+    nodes: Vec<ContributionNode>,
+}
+
+#[derive(Deserialize, Debug)]
+pub(crate) struct PageInfo {
+    #[serde(rename = "hasNextPage")]
+    pub has_next_page: bool,
+    #[serde(rename = "endCursor")]
+    pub end_cursor: Option<String>,
+}
+
+/// Contribution node, the repository is either a direct field or, for
+/// pull requests and issues, nested in the `subject` alias.
+#[derive(Deserialize, Debug)]
+struct ContributionNode {
+    #[serde(rename = "occurredAt")]
+    occurred_at: DateTime<Utc>,
+    repository: Option<RepositoryRef>,
+    subject: Option<ContributionSubject>,
+}
+
+#[derive(Deserialize, Debug)]
+struct ContributionSubject {
+    repository: RepositoryRef,
+}
+
+#[derive(Deserialize, Debug)]
+struct RepositoryRef {
+    #[serde(rename = "nameWithOwner")]
+    name_with_owner: String,
+}
+
+#[derive(Deserialize, Debug)]
+struct CommitContributionsCollection {
+    #[serde(rename = "commitContributionsByRepository")]
+    commit_contributions_by_repository: Vec<RepositoryCommitContributions>,
+}
+
+#[derive(Deserialize, Debug)]
+struct RepositoryCommitContributions {
+    repository: RepositoryRef,
+    contributions: CommitContributionsConnection,
+}
+
+#[derive(Deserialize, Debug)]
+struct CommitContributionsConnection {
+    nodes: Vec<CommitContributionNode>,
+}
+
+#[derive(Deserialize, Debug)]
+struct CommitContributionNode {
+    #[serde(rename = "occurredAt")]
+    occurred_at: DateTime<Utc>,
+    #[serde(rename = "commitCount")]
+    commit_count: u32,
+}
+
+/// Contributions made on a day to a repository.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub(crate) struct RepositoryContributions {
+    pub date: NaiveDate,
+    pub count: u32,
+    pub repository: String,
 }
 
 fn get_year_week(date: NaiveDate) -> YearWeek {
@@ -122,43 +746,141 @@ fn get_year_week(date: NaiveDate) -> YearWeek {
     }
 }
 
-/// Function using GitHub GraphQL API to download target user
-/// activity on the specified date range.
-/// if `maybe_token`is not `None`, it will be used as application
-/// authentication token. This is required to obtain private repositories
-/// contributions.
-pub async fn get_activity(
-    user: UserName<'_>,
-    date_range: DateRange,
-    maybe_token: Option<String>,
-) -> Result<Activity, Box<dyn std::error::Error>> {
-    let number_of_weeks = ((date_range.1 - date_range.0).num_days() as f32 / 7.0).ceil() as usize;
+/// Number of weeks, Monday to Sunday, with at least one day in `date_range`.
+fn number_of_weeks(date_range: DateRange) -> usize {
+    let first_monday = WeekStart::Monday.first_day_of_week(date_range.0);
+    let last_monday = WeekStart::Monday.first_day_of_week(date_range.1);
+    ((last_monday - first_monday).num_days() / 7 + 1) as usize
+}
 
-    let mut contributions: HashMap<YearWeek, HashMap<Weekday, u32>> = HashMap::new();
+/// Weeks of `date_range` without contributions.
+fn empty_weeks(date_range: DateRange) -> WeeklyContributions {
+    date_range
+        .0
+        .iter_weeks()
+        .take(number_of_weeks(date_range))
+        .map(|date| (get_year_week(date), HashMap::new()))
+        .collect()
+}
 
-    // Initialize the activity weeks
-    for date in date_range.0.iter_weeks().take(number_of_weeks) {
-        let year_week = get_year_week(date);
-        contributions.insert(year_week, HashMap::new());
+/// Number of contributions in `weeks` made on `date`.
+fn weekly_contributions_on(weeks: &WeeklyContributions, date: NaiveDate) -> u32 {
+    weeks
+        .get(&get_year_week(date))
+        .and_then(|week| week.get(&date.weekday()))
+        .copied()
+        .unwrap_or(0)
+}
+
+/// Add `count` contributions on `date` to `weeks`.
+fn add_contributions(weeks: &mut WeeklyContributions, date: NaiveDate, count: u32) {
+    let entry = weeks.entry(get_year_week(date)).or_default();
+    *entry.entry(date.weekday()).or_insert(0) += count;
+}
+
+/// Longest span, in days, GitHub accepts between the `from` and `to`
+/// arguments of a single `contributionsCollection` query.
+const MAX_WINDOW_DAYS: i64 = 365;
+
+/// Longest span, in days, of the windows used to query commit contributions.
+/// GitHub aggregates commits per repository and day and only the first 100
+/// days of each repository can be listed without pagination.
+const COMMIT_WINDOW_DAYS: i64 = 100;
+
+/// Maximum number of items GitHub returns per page.
+const PAGE_SIZE: usize = 100;
+
+/// Maximum number of repositories `commitContributionsByRepository` lists,
+/// commits to any other repository are not reported by GitHub.
+const MAX_COMMIT_REPOSITORIES: usize = 100;
+
+/// Maximum number of queries sent at once for the activity of a single
+/// user, sending many more trips the secondary rate limits of GitHub.
+const MAX_CONCURRENT_QUERIES: usize = 4;
+
+/// Split a date range into consecutive, non overlapping windows
+/// no longer than `window_days` days. Both ends of each window are inclusive.
+fn split_date_range(date_range: DateRange, window_days: i64) -> Vec<DateRange> {
+    let mut windows = Vec::new();
+    let mut window_start = date_range.0;
+    while window_start <= date_range.1 {
+        let window_end = std::cmp::min(
+            window_start + chrono::Duration::days(window_days - 1),
+            date_range.1,
+        );
+        windows.push((window_start, window_end));
+        window_start = window_end + chrono::Duration::days(1);
     }
+    windows
+}
 
-    let client = reqwest::Client::new();
+/// Arguments selecting a `contributionsCollection`: whose contributions,
+/// over which window and, optionally, restricted to which organization.
+#[derive(Debug, Clone, Copy)]
+struct CollectionScope<'a> {
+    user: UserName<'a>,
+    window: DateRange,
+    organization_id: Option<&'a str>,
+}
 
-    // Format dates for GraphQL query (ISO 8601 format)
-    let from = format!("{}T00:00:00Z", date_range.0);
-    let to = format!("{}T23:59:59Z", date_range.1);
+/// Send a GraphQL query over the `contributionsCollection` selected by
+/// `scope` and return that collection from the response.
+/// The query receives the `$username`, `$from`, `$to`, `$organizationID`
+/// and `$after` variables.
+async fn query_contributions_collection<T: DeserializeOwned>(
+    client: &GitHubClient,
+    graphql_query: &str,
+    scope: CollectionScope<'_>,
+    after: Option<String>,
+) -> Result<T, GitHubError> {
+    let variables = GraphQLVariables {
+        username: scope.user.to_string(),
+        // Format dates for GraphQL query (ISO 8601 format)
+        from: format!("{}T00:00:00Z", scope.window.0),
+        to: format!("{}T23:59:59Z", scope.window.1),
+        organization_id: scope.organization_id.map(str::to_string),
+        after,
+    };
 
+    let user_not_found = || GitHubError::UserNotFound(scope.user.to_string());
+
+    let data: GraphQLData<T> = client
+        .post_graphql(graphql_query, variables)
+        .await
+        .map_err(|error| match error {
+            GitHubError::GraphQL(errors)
+                if errors.iter().any(|error| error.is_not_found("user")) =>
+            {
+                user_not_found()
+            }
+            error => error,
+        })?;
+
+    data.user
+        .map(|user| user.contributions_collection)
+        .ok_or_else(user_not_found)
+}
+
+/// Download the contribution calendar days of `user`, and the totals of
+/// the calendar, for a single window no longer than [`MAX_WINDOW_DAYS`].
+async fn fetch_contribution_days(
+    client: &GitHubClient,
+    scope: CollectionScope<'_>,
+) -> Result<(Vec<ContributionDay>, ContributionTotals), GitHubError> {
     // GraphQL query to fetch contribution calendar
     let graphql_query = r#"
-        query($username: String!, $from: DateTime!, $to: DateTime!) {
+        query($username: String!, $from: DateTime!, $to: DateTime!, $organizationID: ID) {
             user(login: $username) {
-                contributionsCollection(from: $from, to: $to) {
+                contributionsCollection(from: $from, to: $to, organizationID: $organizationID) {
+                    restrictedContributionsCount
+                    hasAnyRestrictedContributions
                     contributionCalendar {
                         totalContributions
                         weeks {
                             contributionDays {
                                 date
                                 contributionCount
+                                contributionLevel
                             }
                         }
                     }
@@ -167,68 +889,621 @@ pub async fn get_activity(
         }
     "#;
 
-    let request_body = GraphQLRequest {
-        query: graphql_query.to_string(),
-        variables: GraphQLVariables {
-            username: user.to_string(),
-            from,
-            to,
-        },
+    let collection: ContributionsCollection =
+        query_contributions_collection(client, graphql_query, scope, None).await?;
+
+    let totals = ContributionTotals {
+        total_contributions: collection.contribution_calendar.total_contributions,
+        restricted_contributions: collection.restricted_contributions_count,
+        has_any_restricted_contributions: collection.has_any_restricted_contributions,
+    };
+    let days = collection
+        .contribution_calendar
+        .weeks
+        .into_iter()
+        .flat_map(|week| week.contribution_days)
+        .collect();
+    Ok((days, totals))
+}
+
+/// Download only the totals of the contribution calendar of `user` for a
+/// single window no longer than [`MAX_WINDOW_DAYS`].
+async fn fetch_contribution_totals(
+    client: &GitHubClient,
+    scope: CollectionScope<'_>,
+) -> Result<ContributionTotals, GitHubError> {
+    let graphql_query = r#"
+        query($username: String!, $from: DateTime!, $to: DateTime!, $organizationID: ID) {
+            user(login: $username) {
+                contributionsCollection(from: $from, to: $to, organizationID: $organizationID) {
+                    restrictedContributionsCount
+                    hasAnyRestrictedContributions
+                    contributionCalendar {
+                        totalContributions
+                    }
+                }
+            }
+        }
+    "#;
+
+    let collection: TotalsCollection =
+        query_contributions_collection(client, graphql_query, scope, None).await?;
+
+    Ok(ContributionTotals {
+        total_contributions: collection.contribution_calendar.total_contributions,
+        restricted_contributions: collection.restricted_contributions_count,
+        has_any_restricted_contributions: collection.has_any_restricted_contributions,
+    })
+}
+
+/// Download every contribution of the given kind made in `scope`.
+/// Commits are not supported as GitHub only lists them grouped by
+/// repository, see [`fetch_commit_contributions`].
+async fn fetch_kind_contributions(
+    client: &GitHubClient,
+    scope: CollectionScope<'_>,
+    kind: ContributionKind,
+) -> Result<Vec<RepositoryContributions>, GitHubError> {
+    let graphql_query = format!(
+        r#"
+        query($username: String!, $from: DateTime!, $to: DateTime!, $organizationID: ID, $after: String) {{
+            user(login: $username) {{
+                contributionsCollection(from: $from, to: $to, organizationID: $organizationID) {{
+                    contributions: {}(first: {}, after: $after) {{
+                        pageInfo {{
+                            hasNextPage
+                            endCursor
+                        }}
+                        nodes {{
+                            occurredAt
+                            {}
+                        }}
+                    }}
+                }}
+            }}
+        }}
+    "#,
+        kind.collection_field(),
+        PAGE_SIZE,
+        kind.repository_selection()
+    );
+
+    let mut contributions = Vec::new();
+    let mut after = None;
+    loop {
+        let collection: KindContributionsCollection =
+            query_contributions_collection(client, &graphql_query, scope, after).await?;
+        let connection = collection.contributions;
+        contributions.extend(connection.nodes.into_iter().filter_map(|node| {
+            let repository = node
+                .repository
+                .or(node.subject.map(|subject| subject.repository))?;
+            Some(RepositoryContributions {
+                date: node.occurred_at.date_naive(),
+                count: 1,
+                repository: repository.name_with_owner,
+            })
+        }));
+        if !connection.page_info.has_next_page {
+            break;
+        }
+        after = connection.page_info.end_cursor;
+    }
+    Ok(contributions)
+}
+
+/// Download the number of commits per day and repository made in `scope`.
+/// The window is queried in chunks of [`COMMIT_WINDOW_DAYS`] so that no
+/// repository has more daily entries than fit in a single page.
+/// Only [`MAX_COMMIT_REPOSITORIES`] repositories are listed per chunk, the
+/// chunks which may have been truncated are returned too.
+async fn fetch_commit_contributions(
+    client: &GitHubClient,
+    scope: CollectionScope<'_>,
+) -> Result<(Vec<RepositoryContributions>, Vec<DateRange>), GitHubError> {
+    let graphql_query = format!(
+        r#"
+        query($username: String!, $from: DateTime!, $to: DateTime!, $organizationID: ID) {{
+            user(login: $username) {{
+                contributionsCollection(from: $from, to: $to, organizationID: $organizationID) {{
+                    commitContributionsByRepository(maxRepositories: {1}) {{
+                        repository {{
+                            nameWithOwner
+                        }}
+                        contributions(first: {0}) {{
+                            nodes {{
+                                occurredAt
+                                commitCount
+                            }}
+                        }}
+                    }}
+                }}
+            }}
+        }}
+    "#,
+        PAGE_SIZE, MAX_COMMIT_REPOSITORIES
+    );
+
+    // Chunks are queried one after another as the scopes and kinds this
+    // is called for are already queried concurrently
+    let chunks = split_date_range(scope.window, COMMIT_WINDOW_DAYS);
+    let mut collections: Vec<CommitContributionsCollection> = Vec::new();
+    for &chunk in chunks.iter() {
+        let chunk_scope = CollectionScope {
+            window: chunk,
+            ..scope
+        };
+        let collection =
+            query_contributions_collection(client, &graphql_query, chunk_scope, None).await?;
+        collections.push(collection);
+    }
+
+    let truncated_chunks = chunks
+        .iter()
+        .zip(&collections)
+        .filter(|(_, collection)| {
+            collection.commit_contributions_by_repository.len() >= MAX_COMMIT_REPOSITORIES
+        })
+        .map(|(chunk, _)| *chunk)
+        .collect();
+
+    let contributions = collections
+        .into_iter()
+        .flat_map(|collection| collection.commit_contributions_by_repository)
+        .flat_map(|by_repository| {
+            let repository = by_repository.repository.name_with_owner;
+            by_repository
+                .contributions
+                .nodes
+                .into_iter()
+                .map(move |node| RepositoryContributions {
+                    date: node.occurred_at.date_naive(),
+                    count: node.commit_count,
+                    repository: repository.clone(),
+                })
+        })
+        .collect();
+    Ok((contributions, truncated_chunks))
+}
+
+/// Download every contribution of the given kind made in `scope`, along
+/// with the windows in which GitHub may not have listed all of them.
+async fn fetch_contributions_of_kind(
+    client: &GitHubClient,
+    scope: CollectionScope<'_>,
+    kind: ContributionKind,
+) -> Result<
+    (
+        ContributionKind,
+        Vec<RepositoryContributions>,
+        Vec<DateRange>,
+    ),
+    GitHubError,
+> {
+    let (contributions, truncated_windows) = match kind {
+        ContributionKind::Commit => fetch_commit_contributions(client, scope).await?,
+        _ => (
+            fetch_kind_contributions(client, scope, kind).await?,
+            Vec::new(),
+        ),
     };
+    Ok((kind, contributions, truncated_windows))
+}
 
-    // Build the request
-    let mut request = client
-        .post("https://api.github.com/graphql")
-        .header("User-Agent", "gh-trophy")
-        .json(&request_body);
+/// Which data to download from GitHub in [`fetch_raw_contributions`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct RawQuery {
+    /// Daily totals from the contribution calendar.
+    pub calendar: bool,
+    /// Contributions of every kind, with their repository.
+    pub by_kind: bool,
+}
 
-    // Add authentication if token is provided
-    if let Some(token) = maybe_token {
-        request = request.bearer_auth(token);
+impl RawQuery {
+    fn for_options(options: &ActivityOptions) -> Self {
+        let requires_repositories = options.filters.requires_repositories();
+        RawQuery {
+            calendar: !requires_repositories,
+            by_kind: options.by_kind || requires_repositories,
+        }
     }
+}
 
-    // Make the request
-    let response = request.send().await?;
+/// Contributions as downloaded from GitHub, before being filtered
+/// by repository and grouped by week.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub(crate) struct RawContributions {
+    /// Days with contributions in the contribution calendar.
+    pub calendar: Vec<(NaiveDate, u32)>,
+    /// Contributions of each kind.
+    pub by_kind: Vec<(ContributionKind, RepositoryContributions)>,
+    /// Levels of the days with contributions in the contribution calendar.
+    /// Missing in caches written by older versions.
+    #[serde(default)]
+    pub levels: Vec<(NaiveDate, ContributionLevel)>,
+    /// Totals of the contribution calendar of each downloaded window.
+    /// Missing in caches written by older versions.
+    #[serde(default)]
+    pub totals: Vec<(DateRange, ContributionTotals)>,
+    /// Windows in which GitHub listed commits to the maximum number of
+    /// repositories, see [`Activity::truncated_commit_windows`].
+    #[serde(default)]
+    pub truncated_commit_windows: Vec<DateRange>,
+}
 
-    // Check for errors
-    let status = response.status();
-    let response_text = response.text().await?;
+impl RawContributions {
+    /// Contributions made within `range`.
+    pub fn slice(&self, range: DateRange) -> Self {
+        let in_range = |date: &NaiveDate| *date >= range.0 && *date <= range.1;
+        RawContributions {
+            calendar: self
+                .calendar
+                .iter()
+                .filter(|(date, _)| in_range(date))
+                .cloned()
+                .collect(),
+            by_kind: self
+                .by_kind
+                .iter()
+                .filter(|(_, contributions)| in_range(&contributions.date))
+                .cloned()
+                .collect(),
+            levels: self
+                .levels
+                .iter()
+                .filter(|(date, _)| in_range(date))
+                .cloned()
+                .collect(),
+            // Totals can not be split so only whole windows are kept
+            totals: self
+                .totals
+                .iter()
+                .filter(|(window, _)| in_range(&window.0) && in_range(&window.1))
+                .cloned()
+                .collect(),
+            truncated_commit_windows: clip_windows(&self.truncated_commit_windows, range),
+        }
+    }
 
-    if !status.is_success() {
-        return Err(format!("GitHub API error {}: {}", status, response_text).into());
+    /// Totals of the windows covering `date_range`, `None` unless they
+    /// cover every day of it.
+    fn totals_of(&self, date_range: DateRange) -> Option<ContributionTotals> {
+        let mut windows = self.totals.clone();
+        windows.sort_by_key(|(window, _)| window.0);
+        let mut next_day = date_range.0;
+        let mut totals = ContributionTotals::default();
+        for (window, window_totals) in windows {
+            if window.0 != next_day {
+                return None;
+            }
+            next_day = window.1.succ_opt()?;
+            totals = totals.add(window_totals);
+        }
+        (next_day.pred_opt()? == date_range.1).then_some(totals)
     }
 
-    // Parse the GraphQL response
-    let graphql_response: GraphQLResponse = serde_json::from_str(&response_text)?;
+    pub fn extend(&mut self, other: RawContributions) {
+        self.calendar.extend(other.calendar);
+        self.by_kind.extend(other.by_kind);
+        self.levels.extend(other.levels);
+        self.totals.extend(other.totals);
+        self.truncated_commit_windows
+            .extend(other.truncated_commit_windows);
+    }
+}
 
-    // Process the contribution calendar data
-    for week in graphql_response
-        .data
-        .user
-        .contributions_collection
-        .contribution_calendar
-        .weeks
-    {
-        for day in week.contribution_days {
-            if day.contribution_count > 0 {
-                // Parse the date
-                if let Ok(date) = NaiveDate::parse_from_str(&day.date, "%Y-%m-%d") {
-                    if date >= date_range.0 && date <= date_range.1 {
-                        let year_week = get_year_week(date);
-                        let weekday = date.weekday();
+/// Node ids of the given organizations, or a single `None` to query every
+/// contribution if there is no organization. Contributions to several
+/// organizations are obtained by querying each of them separately.
+async fn resolve_organization_ids(
+    client: &GitHubClient,
+    organizations: &[String],
+    known_organization_ids: &HashMap<String, String>,
+) -> Result<Vec<Option<String>>, GitHubError> {
+    if organizations.is_empty() {
+        return Ok(vec![None]);
+    }
+    futures::stream::iter(organizations)
+        .map(|login| async move {
+            match known_organization_ids.get(login) {
+                Some(id) => Ok(Some(id.clone())),
+                None => fetch_organization_id(client, login).await.map(Some),
+            }
+        })
+        .buffered(MAX_CONCURRENT_QUERIES)
+        .try_collect()
+        .await
+}
 
-                        let entry = contributions.entry(year_week).or_insert_with(HashMap::new);
-                        *entry.entry(weekday).or_insert(0) += day.contribution_count;
-                    }
+/// Scopes covering `date_range`, split in windows no longer than
+/// [`MAX_WINDOW_DAYS`], for each of the given organizations.
+fn collection_scopes<'a>(
+    user: UserName<'a>,
+    date_range: DateRange,
+    organization_ids: &'a [Option<String>],
+) -> Vec<CollectionScope<'a>> {
+    split_date_range(date_range, MAX_WINDOW_DAYS)
+        .into_iter()
+        .flat_map(|window| {
+            organization_ids
+                .iter()
+                .map(move |organization_id| CollectionScope {
+                    user,
+                    window,
+                    organization_id: organization_id.as_deref(),
+                })
+        })
+        .collect()
+}
+
+/// Add the totals of `window`, totals of the same window for several
+/// organizations are added up.
+fn add_window_totals(
+    windows: &mut Vec<(DateRange, ContributionTotals)>,
+    window: DateRange,
+    totals: ContributionTotals,
+) {
+    match windows.iter_mut().find(|(other, _)| *other == window) {
+        Some((_, window_totals)) => *window_totals = window_totals.add(totals),
+        None => windows.push((window, totals)),
+    }
+}
+
+/// Download only the totals of the contribution calendar of `user` in
+/// `date_range`, e.g: when cached contributions can not provide them.
+pub(crate) async fn fetch_raw_totals(
+    client: &GitHubClient,
+    user: UserName<'_>,
+    date_range: DateRange,
+    organizations: &[String],
+    known_organization_ids: &HashMap<String, String>,
+) -> Result<Vec<(DateRange, ContributionTotals)>, GitHubError> {
+    let organization_ids =
+        resolve_organization_ids(client, organizations, known_organization_ids).await?;
+    let scopes = collection_scopes(user, date_range, &organization_ids);
+
+    let scopes_totals: Vec<ContributionTotals> = futures::stream::iter(scopes.iter())
+        .map(|scope| fetch_contribution_totals(client, *scope))
+        .buffered(MAX_CONCURRENT_QUERIES)
+        .try_collect()
+        .await?;
+
+    let mut totals = Vec::new();
+    for (scope, scope_totals) in scopes.iter().zip(scopes_totals) {
+        add_window_totals(&mut totals, scope.window, scope_totals);
+    }
+    Ok(totals)
+}
+
+/// Download the contributions of `user` in `date_range`, only counting
+/// contributions to the given organizations if there is any.
+/// Node ids of the organizations missing from `known_organization_ids`
+/// are looked up first.
+/// Ranges longer than one year are split into several windows, at most
+/// [`MAX_CONCURRENT_QUERIES`] queries being sent at once.
+pub(crate) async fn fetch_raw_contributions(
+    client: &GitHubClient,
+    user: UserName<'_>,
+    date_range: DateRange,
+    organizations: &[String],
+    known_organization_ids: &HashMap<String, String>,
+    query: RawQuery,
+) -> Result<RawContributions, GitHubError> {
+    let organization_ids =
+        resolve_organization_ids(client, organizations, known_organization_ids).await?;
+    let scopes = collection_scopes(user, date_range, &organization_ids);
+
+    let mut raw_contributions = RawContributions::default();
+
+    if query.calendar {
+        let scopes_days: Vec<_> = futures::stream::iter(scopes.iter())
+            .map(|scope| fetch_contribution_days(client, *scope))
+            .buffered(MAX_CONCURRENT_QUERIES)
+            .try_collect()
+            .await?;
+
+        // Process the contribution calendar data, each window only contributes
+        // its own days so that calendar weeks shared by two windows are not
+        // counted twice.
+        for (scope, (days, totals)) in scopes.iter().zip(scopes_days) {
+            add_window_totals(&mut raw_contributions.totals, scope.window, totals);
+            for day in days {
+                if day.contribution_count == 0 {
+                    continue;
+                }
+                // Parse the date
+                if let Ok(date) = NaiveDate::parse_from_str(&day.date, "%Y-%m-%d")
+                    && date >= scope.window.0
+                    && date <= scope.window.1
+                {
+                    raw_contributions
+                        .calendar
+                        .push((date, day.contribution_count));
+                    raw_contributions
+                        .levels
+                        .push((date, day.contribution_level));
                 }
             }
         }
     }
 
-    Ok(Activity {
+    if query.by_kind {
+        let kinds_contributions: Vec<_> = futures::stream::iter(scopes.iter().flat_map(|scope| {
+            ContributionKind::ALL
+                .into_iter()
+                .map(move |kind| fetch_contributions_of_kind(client, *scope, kind))
+        }))
+        .buffered(MAX_CONCURRENT_QUERIES)
+        .try_collect()
+        .await?;
+
+        for (kind, kind_contributions, truncated_windows) in kinds_contributions {
+            raw_contributions
+                .truncated_commit_windows
+                .extend(truncated_windows);
+            raw_contributions.by_kind.extend(
+                kind_contributions
+                    .into_iter()
+                    .filter(|contributions| {
+                        contributions.date >= date_range.0 && contributions.date <= date_range.1
+                    })
+                    .map(|contributions| (kind, contributions)),
+            );
+        }
+    }
+
+    Ok(raw_contributions)
+}
+
+/// Parts of `windows` within `date_range`.
+fn clip_windows(windows: &[DateRange], date_range: DateRange) -> Vec<DateRange> {
+    windows
+        .iter()
+        .map(|window| (window.0.max(date_range.0), window.1.min(date_range.1)))
+        .filter(|window| window.0 <= window.1)
+        .collect()
+}
+
+/// Group downloaded contributions by week, applying the filters in `options`.
+fn build_activity(
+    date_range: DateRange,
+    raw_contributions: RawContributions,
+    options: &ActivityOptions,
+) -> Activity {
+    let mut contributions = empty_weeks(date_range);
+    let totals = raw_contributions.totals_of(date_range);
+    let mut truncated_commit_windows =
+        clip_windows(&raw_contributions.truncated_commit_windows, date_range);
+    // Windows of several organizations can be truncated alike
+    truncated_commit_windows.sort();
+    truncated_commit_windows.dedup();
+
+    let filters = &options.filters;
+
+    // Levels are only kept if GitHub provided them for every day with
+    // contributions, which is not the case when the calendar is rebuilt
+    // from filtered contributions or for caches written by older versions.
+    let github_levels = (options.github_levels
+        && !raw_contributions.calendar.is_empty()
+        && raw_contributions.levels.len() == raw_contributions.calendar.len())
+    .then(|| raw_contributions.levels.into_iter().collect());
+
+    for (date, count) in raw_contributions.calendar {
+        add_contributions(&mut contributions, date, count);
+    }
+
+    let mut contributions_by_kind: HashMap<ContributionKind, WeeklyContributions> = HashMap::new();
+
+    if options.by_kind {
+        for kind in ContributionKind::ALL {
+            contributions_by_kind.insert(kind, HashMap::new());
+        }
+    }
+    for (kind, repository_contributions) in raw_contributions.by_kind {
+        if !filters.accepts_repository(&repository_contributions.repository) {
+            continue;
+        }
+        let date = repository_contributions.date;
+        if filters.requires_repositories() {
+            add_contributions(&mut contributions, date, repository_contributions.count);
+        }
+        if options.by_kind {
+            let weeks = contributions_by_kind.entry(kind).or_default();
+            add_contributions(weeks, date, repository_contributions.count);
+        }
+    }
+
+    Activity {
         date_range,
         contributions,
-    })
+        contributions_by_kind,
+        github_levels,
+        totals,
+        truncated_commit_windows,
+    }
+}
+
+/// Function using GitHub GraphQL API to download target user
+/// activity on the specified date range.
+/// Private repositories contributions are only included if `client`
+/// authenticates with a token.
+/// Fails with [`GitHubError::UserNotFound`] if `user` does not exist.
+/// Ranges longer than one year are split into several windows which
+/// are queried concurrently and merged into a single [`Activity`].
+/// When [`ActivityOptions::filters`] restrict repositories, the daily
+/// contributions are rebuilt from the contributions of each kind as the
+/// calendar provided by GitHub can not be filtered by repository.
+/// If [`ActivityOptions::cache`] is set, only the days missing or expired
+/// in the cache are downloaded.
+pub async fn get_activity(
+    client: &GitHubClient,
+    user: UserName<'_>,
+    date_range: DateRange,
+    options: &ActivityOptions,
+) -> Result<Activity, GitHubError> {
+    let organizations = &options.filters.include_organizations;
+    let query = RawQuery::for_options(options);
+
+    let mut raw_contributions = match &options.cache {
+        Some(cache) => {
+            cache
+                .get_or_fetch(
+                    client,
+                    user,
+                    date_range,
+                    organizations,
+                    &options.organization_ids,
+                    query,
+                )
+                .await?
+        }
+        None => {
+            fetch_raw_contributions(
+                client,
+                user,
+                date_range,
+                organizations,
+                &options.organization_ids,
+                query,
+            )
+            .await?
+        }
+    };
+
+    // Cached contributions are split in segments as the range moves, which
+    // loses the totals of the whole range, so they are downloaded again
+    if query.calendar
+        && raw_contributions.totals_of(date_range).is_none()
+        && options.cache.as_ref().is_some_and(|cache| !cache.offline)
+    {
+        raw_contributions.totals = fetch_raw_totals(
+            client,
+            user,
+            date_range,
+            organizations,
+            &options.organization_ids,
+        )
+        .await?;
+    }
+
+    Ok(build_activity(date_range, raw_contributions, options))
+}
+
+/// Download the activity of several users, e.g: the members of a team,
+/// with at most `parallelism` users being downloaded at once.
+/// Activities are returned in the same order as `users`.
+pub async fn get_activities(
+    client: &GitHubClient,
+    users: &[String],
+    date_range: DateRange,
+    options: &ActivityOptions,
+    parallelism: usize,
+) -> Result<Vec<Activity>, GitHubError> {
+    futures::stream::iter(users)
+        .map(|user| get_activity(client, user, date_range, options))
+        .buffered(parallelism.max(1))
+        .try_collect()
+        .await
 }
 
 #[cfg(test)]
@@ -297,6 +1572,10 @@ mod tests {
         let activity = Activity {
             date_range: (start, end),
             contributions: HashMap::new(),
+            contributions_by_kind: HashMap::new(),
+            github_levels: None,
+            totals: None,
+            truncated_commit_windows: Vec::new(),
         };
         assert_eq!(activity.number_of_weeks(), 4);
     }
@@ -308,8 +1587,12 @@ mod tests {
         let activity = Activity {
             date_range: (start, end),
             contributions: HashMap::new(),
+            contributions_by_kind: HashMap::new(),
+            github_levels: None,
+            totals: None,
+            truncated_commit_windows: Vec::new(),
         };
-        // 29 days / 7 = 4.14, should ceil to 5
+        // Tuesday 30th belongs to a 5th week
         assert_eq!(activity.number_of_weeks(), 5);
     }
 
@@ -320,6 +1603,10 @@ mod tests {
         let activity = Activity {
             date_range: (start, end),
             contributions: HashMap::new(),
+            contributions_by_kind: HashMap::new(),
+            github_levels: None,
+            totals: None,
+            truncated_commit_windows: Vec::new(),
         };
         assert_eq!(activity.number_of_weeks(), 1);
     }
@@ -331,6 +1618,10 @@ mod tests {
         let activity = Activity {
             date_range: (start, end),
             contributions: HashMap::new(),
+            contributions_by_kind: HashMap::new(),
+            github_levels: None,
+            totals: None,
+            truncated_commit_windows: Vec::new(),
         };
         let matrix = activity.as_matrix();
         assert_eq!(matrix.len(), 2); // 2 weeks
@@ -339,7 +1630,7 @@ mod tests {
         // All values should be 0
         for row in matrix {
             for value in row {
-                assert_eq!(value, 0);
+                assert_eq!(value, Some(0));
             }
         }
     }
@@ -364,18 +1655,22 @@ mod tests {
         let activity = Activity {
             date_range: (start, end),
             contributions,
+            contributions_by_kind: HashMap::new(),
+            github_levels: None,
+            totals: None,
+            truncated_commit_windows: Vec::new(),
         };
 
         let matrix = activity.as_matrix();
         assert_eq!(matrix.len(), 2); // 2 weeks
 
         // Check Monday (index 0) has 5 contributions
-        assert_eq!(matrix[0][0], 5);
+        assert_eq!(matrix[0][0], Some(5));
         // Check Wednesday (index 2) has 3 contributions
-        assert_eq!(matrix[0][2], 3);
+        assert_eq!(matrix[0][2], Some(3));
         // Check other days are 0
-        assert_eq!(matrix[0][1], 0); // Tuesday
-        assert_eq!(matrix[0][3], 0); // Thursday
+        assert_eq!(matrix[0][1], Some(0)); // Tuesday
+        assert_eq!(matrix[0][3], Some(0)); // Thursday
     }
 
     #[test]
@@ -410,14 +1705,870 @@ mod tests {
         let activity = Activity {
             date_range: (start, end),
             contributions,
+            contributions_by_kind: HashMap::new(),
+            github_levels: None,
+            totals: None,
+            truncated_commit_windows: Vec::new(),
         };
 
         let matrix = activity.as_matrix();
         assert_eq!(matrix.len(), 3); // 3 weeks
 
         // Week 1, Monday
-        assert_eq!(matrix[0][0], 10);
+        assert_eq!(matrix[0][0], Some(10));
         // Week 2, Friday (index 4)
-        assert_eq!(matrix[1][4], 7);
+        assert_eq!(matrix[1][4], Some(7));
+    }
+
+    #[test]
+    fn test_split_date_range_within_one_year() {
+        let start = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+        let end = NaiveDate::from_ymd_opt(2024, 12, 30).unwrap();
+        assert_eq!(
+            split_date_range((start, end), MAX_WINDOW_DAYS),
+            vec![(start, end)]
+        );
+    }
+
+    #[test]
+    fn test_split_date_range_single_day() {
+        let day = NaiveDate::from_ymd_opt(2024, 2, 29).unwrap();
+        assert_eq!(
+            split_date_range((day, day), MAX_WINDOW_DAYS),
+            vec![(day, day)]
+        );
+    }
+
+    #[test]
+    fn test_split_date_range_multiple_years() {
+        let start = NaiveDate::from_ymd_opt(2020, 3, 15).unwrap();
+        let end = NaiveDate::from_ymd_opt(2025, 3, 13).unwrap();
+        let windows = split_date_range((start, end), MAX_WINDOW_DAYS);
+
+        // 1825 days, exactly five windows
+        assert_eq!(windows.len(), 5);
+        assert_eq!(windows.first().unwrap().0, start);
+        assert_eq!(windows.last().unwrap().1, end);
+        for window in &windows {
+            assert!(window.0 <= window.1);
+            assert!((window.1 - window.0).num_days() < MAX_WINDOW_DAYS);
+        }
+        // Windows are contiguous: no day is missing nor fetched twice
+        for pair in windows.windows(2) {
+            assert_eq!(pair[0].1 + chrono::Duration::days(1), pair[1].0);
+        }
+    }
+
+    #[test]
+    fn test_activity_as_matrix_of_kind() {
+        let start = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+        let end = NaiveDate::from_ymd_opt(2024, 1, 14).unwrap();
+
+        let mut reviews = HashMap::new();
+        add_contributions(
+            &mut reviews,
+            NaiveDate::from_ymd_opt(2024, 1, 9).unwrap(),
+            2,
+        );
+        add_contributions(
+            &mut reviews,
+            NaiveDate::from_ymd_opt(2024, 1, 9).unwrap(),
+            1,
+        );
+
+        let mut contributions_by_kind = HashMap::new();
+        contributions_by_kind.insert(ContributionKind::PullRequestReview, reviews);
+
+        let activity = Activity {
+            date_range: (start, end),
+            contributions: HashMap::new(),
+            contributions_by_kind,
+            github_levels: None,
+            totals: None,
+            truncated_commit_windows: Vec::new(),
+        };
+
+        let matrix = activity
+            .as_matrix_of_kind(ContributionKind::PullRequestReview)
+            .unwrap();
+        // Week 2, Tuesday
+        assert_eq!(matrix[1][1], Some(3));
+        assert!(
+            activity
+                .as_matrix_of_kind(ContributionKind::Commit)
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn test_activity_filters_empty_accepts_everything() {
+        let filters = ActivityFilters::default();
+        assert!(!filters.requires_repositories());
+        assert!(filters.accepts_repository("pfcoperez/gh-trophy"));
+    }
+
+    #[test]
+    fn test_activity_filters_repositories() {
+        let filters = ActivityFilters {
+            include_repositories: vec!["acme/flagship".to_string()],
+            ..Default::default()
+        };
+        assert!(filters.requires_repositories());
+        assert!(filters.accepts_repository("ACME/Flagship"));
+        assert!(!filters.accepts_repository("acme/other"));
+
+        let filters = ActivityFilters {
+            exclude_repositories: vec!["acme/noisy".to_string()],
+            ..Default::default()
+        };
+        assert!(filters.accepts_repository("acme/flagship"));
+        assert!(!filters.accepts_repository("acme/noisy"));
+    }
+
+    #[test]
+    fn test_activity_filters_organizations() {
+        let filters = ActivityFilters {
+            include_organizations: vec!["acme".to_string()],
+            ..Default::default()
+        };
+        // Solved with `organizationID`, no need to look at repositories
+        assert!(!filters.requires_repositories());
+        assert!(filters.accepts_repository("acme/flagship"));
+        assert!(!filters.accepts_repository("pfcoperez/gh-trophy"));
+
+        let filters = ActivityFilters {
+            exclude_organizations: vec!["acme".to_string()],
+            ..Default::default()
+        };
+        assert!(filters.requires_repositories());
+        assert!(!filters.accepts_repository("acme/flagship"));
+        assert!(filters.accepts_repository("pfcoperez/gh-trophy"));
+    }
+
+    #[test]
+    fn test_contribution_node_repository() {
+        let json = r#"{
+            "pageInfo": {"hasNextPage": false, "endCursor": null},
+            "nodes": [
+                {"occurredAt": "2024-03-01T10:00:00Z", "subject": {"repository": {"nameWithOwner": "acme/a"}}},
+                {"occurredAt": "2024-03-02T10:00:00Z", "repository": {"nameWithOwner": "acme/b"}}
+            ]
+        }"#;
+        let connection: ContributionsConnection = serde_json::from_str(json).unwrap();
+        assert!(!connection.page_info.has_next_page);
+        let node = &connection.nodes[0];
+        assert!(node.repository.is_none());
+        assert_eq!(
+            node.subject.as_ref().unwrap().repository.name_with_owner,
+            "acme/a"
+        );
+        assert_eq!(
+            connection.nodes[1]
+                .repository
+                .as_ref()
+                .unwrap()
+                .name_with_owner,
+            "acme/b"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_get_activity_from_mock_server() {
+        use crate::github::client::GitHubEndpoint;
+        use wiremock::matchers::{method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        let body = serde_json::json!({
+            "data": {"user": {"contributionsCollection": {
+                "restrictedContributionsCount": 5,
+                "hasAnyRestrictedContributions": true,
+                "contributionCalendar": {
+                "totalContributions": 7,
+                "weeks": [{"contributionDays": [
+                    {"date": "2023-12-31", "contributionCount": 4, "contributionLevel": "FOURTH_QUARTILE"},
+                    {"date": "2024-01-01", "contributionCount": 2, "contributionLevel": "THIRD_QUARTILE"},
+                    {"date": "2024-01-10", "contributionCount": 1, "contributionLevel": "FIRST_QUARTILE"}
+                ]}]
+            }}}}
+        });
+        Mock::given(method("POST"))
+            .and(path("/graphql"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(body))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let client =
+            GitHubClient::new(None).with_endpoint(GitHubEndpoint::from_api_url(&server.uri()));
+        let start = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+        let end = NaiveDate::from_ymd_opt(2024, 1, 14).unwrap();
+        let activity = get_activity(&client, "octocat", (start, end), &Default::default())
+            .await
+            .unwrap();
+
+        let matrix = activity.as_matrix();
+        assert_eq!(matrix[0][0], Some(2));
+        assert_eq!(matrix[1][2], Some(1));
+        // Days out of the range are ignored
+        assert_eq!(matrix.iter().flatten().flatten().sum::<u32>(), 3);
+        assert_eq!(
+            activity.totals,
+            Some(ContributionTotals {
+                total_contributions: 7,
+                restricted_contributions: 5,
+                has_any_restricted_contributions: true,
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn test_get_activity_totals_from_cache() {
+        use crate::github::cache::ActivityCache;
+        use crate::github::client::GitHubEndpoint;
+        use wiremock::matchers::method;
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        let body = serde_json::json!({
+            "data": {"user": {"contributionsCollection": {
+                "restrictedContributionsCount": 1,
+                "hasAnyRestrictedContributions": true,
+                "contributionCalendar": {
+                "totalContributions": 2,
+                "weeks": [{"contributionDays": [
+                    {"date": "2024-01-02", "contributionCount": 2, "contributionLevel": "FOURTH_QUARTILE"}
+                ]}]
+            }}}}
+        });
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(body))
+            .mount(&server)
+            .await;
+
+        let dir = tempfile::tempdir().unwrap();
+        let client =
+            GitHubClient::new(None).with_endpoint(GitHubEndpoint::from_api_url(&server.uri()));
+        let options = ActivityOptions {
+            cache: Some(ActivityCache::new(dir.path().to_path_buf())),
+            ..Default::default()
+        };
+        let date = |day| NaiveDate::from_ymd_opt(2024, 1, day).unwrap();
+        let expected = Some(ContributionTotals {
+            total_contributions: 2,
+            restricted_contributions: 1,
+            has_any_restricted_contributions: true,
+        });
+
+        let activity = get_activity(&client, "octocat", (date(1), date(7)), &options)
+            .await
+            .unwrap();
+        assert_eq!(activity.totals, expected);
+        assert_eq!(server.received_requests().await.unwrap().len(), 1);
+
+        // Only the new day is downloaded, which splits the cached segments,
+        // so the totals of the moved range are downloaded again
+        let activity = get_activity(&client, "octocat", (date(2), date(8)), &options)
+            .await
+            .unwrap();
+        assert_eq!(activity.totals, expected);
+        let requests = server.received_requests().await.unwrap();
+        assert_eq!(requests.len(), 3);
+        assert!(!String::from_utf8_lossy(&requests[2].body).contains("weeks"));
+    }
+
+    #[test]
+    fn test_raw_contributions_totals() {
+        let date = |day| NaiveDate::from_ymd_opt(2024, 1, day).unwrap();
+        let totals = |count| ContributionTotals {
+            total_contributions: count,
+            restricted_contributions: count,
+            has_any_restricted_contributions: count > 0,
+        };
+        let raw_contributions = RawContributions {
+            totals: vec![
+                ((date(11), date(20)), totals(2)),
+                ((date(1), date(10)), totals(1)),
+            ],
+            ..Default::default()
+        };
+
+        assert_eq!(
+            raw_contributions.totals_of((date(1), date(20))),
+            Some(totals(3))
+        );
+        // Windows not covering the whole range
+        assert_eq!(raw_contributions.totals_of((date(1), date(21))), None);
+        assert_eq!(
+            raw_contributions
+                .slice((date(2), date(20)))
+                .totals_of((date(2), date(20))),
+            None
+        );
+        assert_eq!(
+            RawContributions::default().totals_of((date(1), date(1))),
+            None
+        );
+    }
+
+    #[tokio::test]
+    async fn test_get_activity_github_levels() {
+        use crate::github::client::GitHubEndpoint;
+        use wiremock::matchers::method;
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        let body = serde_json::json!({
+            "data": {"user": {"contributionsCollection": {
+                "restrictedContributionsCount": 0,
+                "hasAnyRestrictedContributions": false,
+                "contributionCalendar": {
+                "totalContributions": 3,
+                "weeks": [{"contributionDays": [
+                    {"date": "2024-01-01", "contributionCount": 0, "contributionLevel": "NONE"},
+                    {"date": "2024-01-02", "contributionCount": 2, "contributionLevel": "SECOND_QUARTILE"},
+                    {"date": "2024-01-03", "contributionCount": 1, "contributionLevel": "FIRST_QUARTILE"}
+                ]}]
+            }}}}
+        });
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(body))
+            .mount(&server)
+            .await;
+
+        let client =
+            GitHubClient::new(None).with_endpoint(GitHubEndpoint::from_api_url(&server.uri()));
+        let start = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+        let end = NaiveDate::from_ymd_opt(2024, 1, 7).unwrap();
+        let options = ActivityOptions {
+            github_levels: true,
+            ..Default::default()
+        };
+        let activity = get_activity(&client, "octocat", (start, end), &options)
+            .await
+            .unwrap();
+
+        // Computed from the quartiles, 2 would be the highest level
+        let levels = activity.as_level_matrix(WeekStart::Monday);
+        assert_eq!(levels[0][0], Some(ContributionLevel::None));
+        assert_eq!(levels[0][1], Some(ContributionLevel::SecondQuartile));
+        assert_eq!(levels[0][2], Some(ContributionLevel::FirstQuartile));
+    }
+
+    #[tokio::test]
+    async fn test_get_activity_truncated_commits() {
+        use crate::github::client::GitHubEndpoint;
+        use wiremock::matchers::method;
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        let repositories: Vec<serde_json::Value> = (0..MAX_COMMIT_REPOSITORIES)
+            .map(|index| {
+                serde_json::json!({
+                    "repository": {"nameWithOwner": format!("acme/repo-{}", index)},
+                    "contributions": {"nodes": [
+                        {"occurredAt": "2024-01-02T10:00:00Z", "commitCount": 1}
+                    ]}
+                })
+            })
+            .collect();
+        // Answers the calendar, kind and commit queries alike
+        let body = serde_json::json!({
+            "data": {"user": {"contributionsCollection": {
+                "restrictedContributionsCount": 0,
+                "hasAnyRestrictedContributions": false,
+                "contributionCalendar": {"totalContributions": 0, "weeks": []},
+                "contributions": {
+                    "pageInfo": {"hasNextPage": false, "endCursor": null},
+                    "nodes": []
+                },
+                "commitContributionsByRepository": repositories
+            }}}
+        });
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(body))
+            .mount(&server)
+            .await;
+
+        let client =
+            GitHubClient::new(None).with_endpoint(GitHubEndpoint::from_api_url(&server.uri()));
+        let date = |day| NaiveDate::from_ymd_opt(2024, 1, day).unwrap();
+        let options = ActivityOptions {
+            by_kind: true,
+            ..Default::default()
+        };
+        let activity = get_activity(&client, "octocat", (date(1), date(14)), &options)
+            .await
+            .unwrap();
+
+        assert_eq!(activity.truncated_commit_windows, vec![(date(1), date(14))]);
+        assert_eq!(
+            activity.contributions_by_kind[&ContributionKind::Commit][&get_year_week(date(2))]
+                [&Weekday::Tue],
+            MAX_COMMIT_REPOSITORIES as u32
+        );
+        let sliced = activity.slice((date(8), date(20))).unwrap();
+        assert_eq!(sliced.truncated_commit_windows, vec![(date(8), date(14))]);
+    }
+
+    #[tokio::test]
+    async fn test_get_activity_bounds_concurrent_queries() {
+        use crate::github::client::GitHubEndpoint;
+        use std::sync::{Arc, Mutex};
+        use std::time::{Duration, Instant};
+        use wiremock::matchers::method;
+        use wiremock::{Mock, MockServer, Request, Respond, ResponseTemplate};
+
+        /// Record when each request arrives, responses are delayed so
+        /// that requests sent at once arrive before any response.
+        struct Arrivals(Arc<Mutex<Vec<Instant>>>, ResponseTemplate);
+
+        impl Respond for Arrivals {
+            fn respond(&self, _: &Request) -> ResponseTemplate {
+                self.0.lock().unwrap().push(Instant::now());
+                self.1.clone()
+            }
+        }
+
+        let server = MockServer::start().await;
+        let body = serde_json::json!({
+            "data": {"user": {"contributionsCollection": {
+                "restrictedContributionsCount": 0,
+                "hasAnyRestrictedContributions": false,
+                "contributionCalendar": {"totalContributions": 0, "weeks": []},
+                "contributions": {
+                    "pageInfo": {"hasNextPage": false, "endCursor": null},
+                    "nodes": []
+                },
+                "commitContributionsByRepository": []
+            }}}
+        });
+        let delay = Duration::from_millis(100);
+        let arrivals = Arc::new(Mutex::new(Vec::new()));
+        let response = ResponseTemplate::new(200)
+            .set_body_json(body)
+            .set_delay(delay);
+        Mock::given(method("POST"))
+            .respond_with(Arrivals(arrivals.clone(), response))
+            .mount(&server)
+            .await;
+
+        let client =
+            GitHubClient::new(None).with_endpoint(GitHubEndpoint::from_api_url(&server.uri()));
+        let start = NaiveDate::from_ymd_opt(2021, 1, 1).unwrap();
+        let end = NaiveDate::from_ymd_opt(2023, 12, 31).unwrap();
+        let options = ActivityOptions {
+            by_kind: true,
+            ..Default::default()
+        };
+        get_activity(&client, "octocat", (start, end), &options)
+            .await
+            .unwrap();
+
+        // Three windows, queried for the calendar and each kind
+        let arrivals = arrivals.lock().unwrap();
+        assert!(arrivals.len() > 3 * (1 + ContributionKind::ALL.len()));
+        let max_concurrent = arrivals
+            .iter()
+            .map(|first| {
+                arrivals
+                    .iter()
+                    .filter(|other| **other >= *first && **other - *first < delay * 4 / 5)
+                    .count()
+            })
+            .max()
+            .unwrap();
+        assert_eq!(max_concurrent, MAX_CONCURRENT_QUERIES);
+    }
+
+    #[tokio::test]
+    async fn test_get_activity_user_not_found() {
+        use crate::github::client::GitHubEndpoint;
+        use wiremock::matchers::method;
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        let body = serde_json::json!({
+            "data": {"user": null},
+            "errors": [{
+                "type": "NOT_FOUND",
+                "path": ["user"],
+                "locations": [{"line": 3, "column": 13}],
+                "message": "Could not resolve to a User with the login of 'nobody'."
+            }]
+        });
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(body))
+            .mount(&server)
+            .await;
+
+        let client =
+            GitHubClient::new(None).with_endpoint(GitHubEndpoint::from_api_url(&server.uri()));
+        let start = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+        let end = NaiveDate::from_ymd_opt(2024, 1, 14).unwrap();
+        let result = get_activity(&client, "nobody", (start, end), &Default::default()).await;
+
+        assert!(matches!(result, Err(GitHubError::UserNotFound(user)) if user == "nobody"));
+    }
+
+    #[test]
+    fn test_activity_number_of_weeks_mid_week() {
+        // Wednesday to the next Tuesday spans two weeks
+        let start = NaiveDate::from_ymd_opt(2024, 1, 3).unwrap();
+        let end = NaiveDate::from_ymd_opt(2024, 1, 9).unwrap();
+        assert_eq!(number_of_weeks((start, end)), 2);
+    }
+
+    #[test]
+    fn test_activity_as_matrix_across_new_year() {
+        // Friday 2024-12-20 to Friday 2025-01-10
+        let start = NaiveDate::from_ymd_opt(2024, 12, 20).unwrap();
+        let end = NaiveDate::from_ymd_opt(2025, 1, 10).unwrap();
+        let activity = Activity::from_daily_contributions(
+            (start, end),
+            vec![
+                (start, 1),
+                (NaiveDate::from_ymd_opt(2024, 12, 31).unwrap(), 2),
+                (NaiveDate::from_ymd_opt(2025, 1, 6).unwrap(), 3),
+                (end, 4),
+            ],
+        );
+
+        let matrix = activity.as_matrix();
+        assert_eq!(matrix.len(), 4);
+        assert_eq!(matrix[0][4], Some(1));
+        // 2024-12-31 is in ISO week 1 of 2025
+        assert_eq!(matrix[2][1], Some(2));
+        assert_eq!(matrix[3][0], Some(3));
+        assert_eq!(matrix[3][4], Some(4));
+        assert_eq!(matrix.iter().flatten().flatten().sum::<u32>(), 10);
+    }
+
+    #[test]
+    fn test_activity_as_matrix_53_week_year() {
+        // 2020 has 53 ISO weeks, Monday 2020-12-28 starts week 53
+        let start = NaiveDate::from_ymd_opt(2020, 12, 21).unwrap();
+        let end = NaiveDate::from_ymd_opt(2021, 1, 10).unwrap();
+        let activity = Activity::from_daily_contributions(
+            (start, end),
+            vec![
+                (NaiveDate::from_ymd_opt(2020, 12, 28).unwrap(), 5),
+                (NaiveDate::from_ymd_opt(2021, 1, 4).unwrap(), 6),
+            ],
+        );
+
+        let matrix = activity.as_matrix();
+        assert_eq!(matrix.len(), 3);
+        assert_eq!(matrix[1][0], Some(5));
+        assert_eq!(matrix[2][0], Some(6));
+    }
+
+    #[test]
+    fn test_activity_as_matrix_same_week_number_in_two_years() {
+        let start = NaiveDate::from_ymd_opt(2023, 1, 16).unwrap();
+        let end = NaiveDate::from_ymd_opt(2024, 1, 21).unwrap();
+        // Both days are Mondays of ISO week 3
+        let activity = Activity::from_daily_contributions(
+            (start, end),
+            vec![
+                (start, 1),
+                (NaiveDate::from_ymd_opt(2024, 1, 15).unwrap(), 2),
+            ],
+        );
+
+        let matrix = activity.as_matrix();
+        assert_eq!(matrix.len(), 53);
+        assert_eq!(matrix[0][0], Some(1));
+        assert_eq!(matrix[52][0], Some(2));
+    }
+
+    #[test]
+    fn test_activity_as_matrix_last_365_days() {
+        // Same range as the CLI default, starting mid-week
+        let end = NaiveDate::from_ymd_opt(2025, 11, 13).unwrap();
+        let start = end - chrono::Duration::days(365);
+        let activity = Activity::from_daily_contributions((start, end), vec![(start, 1), (end, 2)]);
+
+        let matrix = activity.as_matrix();
+        assert_eq!(matrix.len(), 53);
+        assert_eq!(
+            matrix[0][start.weekday().num_days_from_monday() as usize],
+            Some(1)
+        );
+        assert_eq!(
+            matrix[52][end.weekday().num_days_from_monday() as usize],
+            Some(2)
+        );
+    }
+
+    #[test]
+    fn test_activity_as_matrix_out_of_range_days() {
+        // Wednesday 2024-01-03 to Tuesday 2024-01-09
+        let start = NaiveDate::from_ymd_opt(2024, 1, 3).unwrap();
+        let end = NaiveDate::from_ymd_opt(2024, 1, 9).unwrap();
+        let activity = Activity::from_daily_contributions((start, end), vec![(start, 1)]);
+
+        let matrix = activity.as_matrix();
+        assert_eq!(
+            matrix,
+            vec![
+                vec![None, None, Some(1), Some(0), Some(0), Some(0), Some(0)],
+                vec![Some(0), Some(0), None, None, None, None, None],
+            ]
+        );
+    }
+
+    #[test]
+    fn test_quartiles() {
+        let quartiles = Quartiles::of([0, 0, 1, 2, 3, 4, 5, 6, 7, 8]);
+        assert_eq!(quartiles, Quartiles([2, 4, 6]));
+        assert_eq!(quartiles.level(0), ContributionLevel::None);
+        assert_eq!(quartiles.level(1), ContributionLevel::FirstQuartile);
+        assert_eq!(quartiles.level(4), ContributionLevel::SecondQuartile);
+        assert_eq!(quartiles.level(5), ContributionLevel::ThirdQuartile);
+        assert_eq!(quartiles.level(100), ContributionLevel::FourthQuartile);
+        assert_eq!(Quartiles::of([]), Quartiles([0, 0, 0]));
+    }
+
+    #[test]
+    fn test_activity_as_level_matrix() {
+        // Wednesday 2024-01-03 to Tuesday 2024-01-09
+        let start = NaiveDate::from_ymd_opt(2024, 1, 3).unwrap();
+        let end = NaiveDate::from_ymd_opt(2024, 1, 9).unwrap();
+        let activity = Activity::from_daily_contributions(
+            (start, end),
+            (1..=5).map(|day| (start + chrono::Duration::days(day), day as u32 * day as u32)),
+        );
+
+        let levels: Vec<Vec<Option<u32>>> = activity
+            .as_level_matrix(WeekStart::Monday)
+            .into_iter()
+            .map(|week| {
+                week.into_iter()
+                    .map(|level| level.map(ContributionLevel::value))
+                    .collect()
+            })
+            .collect();
+        assert_eq!(
+            levels,
+            vec![
+                vec![None, None, Some(0), Some(1), Some(1), Some(2), Some(3)],
+                vec![Some(4), Some(0), None, None, None, None, None],
+            ]
+        );
+    }
+
+    #[test]
+    fn test_week_start() {
+        let sunday = NaiveDate::from_ymd_opt(2024, 1, 7).unwrap();
+        let monday = NaiveDate::from_ymd_opt(2024, 1, 8).unwrap();
+        assert_eq!(WeekStart::Monday.day_index(Weekday::Mon), 0);
+        assert_eq!(WeekStart::Monday.day_index(Weekday::Sun), 6);
+        assert_eq!(WeekStart::Sunday.day_index(Weekday::Sun), 0);
+        assert_eq!(WeekStart::Sunday.day_index(Weekday::Sat), 6);
+        assert_eq!(WeekStart::Sunday.first_day_of_week(monday), sunday);
+        assert_eq!(
+            WeekStart::Monday.first_day_of_week(sunday),
+            monday - chrono::Duration::days(7)
+        );
+    }
+
+    #[test]
+    fn test_activity_as_calendar_matrix_sunday_first() {
+        // Wednesday 2024-01-03 to Monday 2024-01-15
+        let start = NaiveDate::from_ymd_opt(2024, 1, 3).unwrap();
+        let end = NaiveDate::from_ymd_opt(2024, 1, 15).unwrap();
+        let activity = Activity::from_daily_contributions(
+            (start, end),
+            vec![(NaiveDate::from_ymd_opt(2024, 1, 7).unwrap(), 2), (end, 3)],
+        );
+
+        let matrix = activity.as_calendar_matrix(WeekStart::Sunday);
+        assert_eq!(
+            matrix,
+            vec![
+                vec![None, None, None, Some(0), Some(0), Some(0), Some(0)],
+                vec![
+                    Some(2),
+                    Some(0),
+                    Some(0),
+                    Some(0),
+                    Some(0),
+                    Some(0),
+                    Some(0)
+                ],
+                vec![Some(0), Some(3), None, None, None, None, None],
+            ]
+        );
+
+        let matrix = activity.as_calendar_matrix(WeekStart::Monday);
+        assert_eq!(matrix.len(), 3);
+        assert_eq!(matrix[0][..3], [None, None, Some(0)]);
+        assert_eq!(matrix[0][6], Some(2));
+        assert_eq!(matrix[2][..2], [Some(3), None]);
+    }
+
+    #[test]
+    fn test_activity_split_by_year() {
+        let date = |year, month, day| NaiveDate::from_ymd_opt(year, month, day).unwrap();
+        let mut activity = Activity::from_daily_contributions(
+            (date(2022, 12, 1), date(2024, 2, 15)),
+            vec![
+                (date(2022, 12, 31), 3),
+                (date(2023, 1, 1), 1),
+                (date(2023, 6, 5), 2),
+                (date(2024, 2, 15), 4),
+            ],
+        );
+        activity.github_levels = Some(HashMap::from([
+            (date(2022, 12, 31), ContributionLevel::FourthQuartile),
+            (date(2024, 2, 15), ContributionLevel::FirstQuartile),
+        ]));
+        let years = activity.split_by_year();
+
+        assert_eq!(
+            years.iter().map(|year| year.date_range).collect::<Vec<_>>(),
+            vec![
+                (date(2022, 12, 1), date(2022, 12, 31)),
+                (date(2023, 1, 1), date(2023, 12, 31)),
+                (date(2024, 1, 1), date(2024, 2, 15)),
+            ]
+        );
+        assert_eq!(years[0].contributions_on(date(2022, 12, 31)), 3);
+        // 2022-12-31 and 2023-01-01 share a week but not a year
+        assert_eq!(years[0].contributions_on(date(2023, 1, 1)), 0);
+        assert_eq!(years[1].contributions_on(date(2023, 1, 1)), 1);
+        assert_eq!(years[1].contributions_on(date(2023, 6, 5)), 2);
+        assert_eq!(years[1].number_of_weeks(), 53);
+        assert_eq!(years[2].contributions_on(date(2024, 2, 15)), 4);
+        assert_eq!(
+            years[2].github_levels,
+            Some(HashMap::from([(
+                date(2024, 2, 15),
+                ContributionLevel::FirstQuartile
+            )]))
+        );
+        assert!(
+            activity
+                .slice((date(2025, 1, 1), date(2025, 2, 1)))
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn test_activity_merge() {
+        let day = |day| NaiveDate::from_ymd_opt(2024, 1, day).unwrap();
+        let first =
+            Activity::from_daily_contributions((day(1), day(7)), vec![(day(1), 3), (day(2), 1)]);
+        let second = Activity::from_daily_contributions(
+            (day(3), day(10)),
+            vec![(day(1), 5), (day(2), 2), (day(10), 4)],
+        );
+        let activities = [first, second];
+
+        let sum = Activity::merge(&activities, MergeMode::Sum).unwrap();
+        assert_eq!(sum.date_range, (day(1), day(10)));
+        assert_eq!(sum.contributions_on(day(1)), 3);
+        assert_eq!(sum.contributions_on(day(10)), 4);
+
+        let first = Activity::from_daily_contributions((day(1), day(7)), vec![(day(2), 1)]);
+        let second = Activity::from_daily_contributions((day(1), day(7)), vec![(day(2), 4)]);
+        let activities = [first, second];
+        assert_eq!(
+            Activity::merge(&activities, MergeMode::Sum)
+                .unwrap()
+                .contributions_on(day(2)),
+            5
+        );
+        assert_eq!(
+            Activity::merge(&activities, MergeMode::Max)
+                .unwrap()
+                .contributions_on(day(2)),
+            4
+        );
+        assert_eq!(
+            Activity::merge(&activities, MergeMode::ActiveMembers)
+                .unwrap()
+                .contributions_on(day(2)),
+            2
+        );
+        assert!(Activity::merge(&[], MergeMode::Sum).is_none());
+    }
+
+    #[test]
+    fn test_activity_merge_by_kind() {
+        let start = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+        let end = NaiveDate::from_ymd_opt(2024, 1, 7).unwrap();
+        let with_reviews = |count| {
+            let mut reviews = HashMap::new();
+            add_contributions(&mut reviews, start, count);
+            Activity {
+                date_range: (start, end),
+                contributions: reviews.clone(),
+                contributions_by_kind: HashMap::from([(
+                    ContributionKind::PullRequestReview,
+                    reviews,
+                )]),
+                github_levels: None,
+                totals: None,
+                truncated_commit_windows: Vec::new(),
+            }
+        };
+
+        let merged = Activity::merge(&[with_reviews(1), with_reviews(2)], MergeMode::Sum).unwrap();
+        assert_eq!(
+            merged
+                .as_matrix_of_kind(ContributionKind::PullRequestReview)
+                .unwrap()[0][0],
+            Some(3)
+        );
+
+        let without_kinds = Activity::from_daily_contributions((start, end), vec![]);
+        let merged = Activity::merge(&[with_reviews(1), without_kinds], MergeMode::Sum).unwrap();
+        assert!(merged.contributions_by_kind.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_get_activities() {
+        use crate::github::client::GitHubEndpoint;
+        use wiremock::matchers::{body_partial_json, method};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        for (user, count) in [("alice", 1), ("bob", 2)] {
+            let body = serde_json::json!({
+                "data": {"user": {"contributionsCollection": {
+                "restrictedContributionsCount": 0,
+                "hasAnyRestrictedContributions": false,
+                "contributionCalendar": {
+                    "totalContributions": count,
+                    "weeks": [{"contributionDays": [
+                        {"date": "2024-01-02", "contributionCount": count, "contributionLevel": "FOURTH_QUARTILE"}
+                    ]}]
+                }}}}
+            });
+            Mock::given(method("POST"))
+                .and(body_partial_json(
+                    serde_json::json!({"variables": {"username": user}}),
+                ))
+                .respond_with(ResponseTemplate::new(200).set_body_json(body))
+                .mount(&server)
+                .await;
+        }
+
+        let client =
+            GitHubClient::new(None).with_endpoint(GitHubEndpoint::from_api_url(&server.uri()));
+        let start = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+        let end = NaiveDate::from_ymd_opt(2024, 1, 7).unwrap();
+        let users = vec!["alice".to_string(), "bob".to_string()];
+        let activities = get_activities(&client, &users, (start, end), &Default::default(), 1)
+            .await
+            .unwrap();
+
+        let counts: Vec<u32> = activities
+            .iter()
+            .map(|activity| activity.contributions_on(NaiveDate::from_ymd_opt(2024, 1, 2).unwrap()))
+            .collect();
+        assert_eq!(counts, vec![1, 2]);
     }
 }
@@ -0,0 +1,440 @@
+use chrono::{DateTime, NaiveDate, TimeDelta, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use crate::github::activity::{DateRange, RawContributions, RawQuery, fetch_raw_contributions};
+use crate::github::client::GitHubClient;
+use crate::github::error::GitHubError;
+
+/// Days downloaded at least this many days after they happened are
+/// considered final and reused no matter how old the cache entry is.
+const SETTLED_AFTER_DAYS: i64 = 7;
+
+/// On-disk cache of the contributions downloaded by
+/// [`get_activity`](crate::github::activity::get_activity).
+/// Each user, organizations filter, kind of query and token gets its own
+/// JSON file with the days downloaded so far, as anonymous clients only
+/// see public contributions. Only missing days, and recent days
+/// downloaded more than `ttl` ago, are downloaded again.
+#[derive(Debug, Clone)]
+pub struct ActivityCache {
+    /// Directory the cache files are stored in.
+    pub dir: PathBuf,
+    /// How long downloaded days which were not settled yet are reused.
+    pub ttl: TimeDelta,
+    /// Never download anything, fail with [`GitHubError::NotCached`] if
+    /// some of the requested days have never been downloaded.
+    pub offline: bool,
+}
+
+impl ActivityCache {
+    pub fn new(dir: PathBuf) -> Self {
+        ActivityCache {
+            dir,
+            ttl: TimeDelta::hours(12),
+            offline: false,
+        }
+    }
+
+    /// Default cache location: `$XDG_CACHE_HOME/gh-trophy`, falling back to
+    /// `$HOME/.cache/gh-trophy` and `.gh-trophy-cache` in the working directory.
+    pub fn default_dir() -> PathBuf {
+        std::env::var_os("XDG_CACHE_HOME")
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".cache")))
+            .map(|cache| cache.join("gh-trophy"))
+            .unwrap_or_else(|| PathBuf::from(".gh-trophy-cache"))
+    }
+
+    fn entry_path(
+        &self,
+        client: &GitHubClient,
+        user: &str,
+        organizations: &[String],
+        query: RawQuery,
+    ) -> PathBuf {
+        let host = reqwest::Url::parse(&client.endpoint().graphql_url)
+            .ok()
+            .and_then(|url| {
+                let host = url.host_str()?.to_string();
+                Some(match url.port() {
+                    Some(port) => format!("{}-{}", host, port),
+                    None => host,
+                })
+            })
+            .unwrap_or_default();
+        let mut organizations: Vec<String> = organizations
+            .iter()
+            .map(|login| login.to_lowercase())
+            .collect();
+        organizations.sort();
+
+        let mut name = user.to_lowercase();
+        for organization in organizations {
+            name.push('+');
+            name.push_str(&organization);
+        }
+        if query.calendar {
+            name.push_str(".calendar");
+        }
+        if query.by_kind {
+            name.push_str(".kinds");
+        }
+        name.push('@');
+        name.push_str(&host);
+        match client.token_fingerprint() {
+            Some(fingerprint) => name.push_str(&format!(".token-{}", fingerprint)),
+            None => name.push_str(".public"),
+        }
+
+        let file_name: String = name
+            .chars()
+            .map(|c| {
+                if c.is_ascii_alphanumeric() || "+.@-_".contains(c) {
+                    c
+                } else {
+                    '_'
+                }
+            })
+            .collect();
+        self.dir.join(format!("{}.json", file_name))
+    }
+
+    async fn load(&self, path: &PathBuf) -> Result<CacheEntry, GitHubError> {
+        match tokio::fs::read(path).await {
+            Ok(bytes) => Ok(serde_json::from_slice(&bytes)?),
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => Ok(CacheEntry::default()),
+            Err(error) => Err(GitHubError::Cache(error)),
+        }
+    }
+
+    async fn store(&self, path: &PathBuf, entry: &CacheEntry) -> Result<(), GitHubError> {
+        tokio::fs::create_dir_all(&self.dir)
+            .await
+            .map_err(GitHubError::Cache)?;
+        tokio::fs::write(path, serde_json::to_vec(entry)?)
+            .await
+            .map_err(GitHubError::Cache)?;
+        Ok(())
+    }
+
+    /// Obtain the contributions in `date_range` from the cache, downloading
+    /// the days missing or expired.
+    pub(crate) async fn get_or_fetch(
+        &self,
+        client: &GitHubClient,
+        user: &str,
+        date_range: DateRange,
+        organizations: &[String],
+        organization_ids: &HashMap<String, String>,
+        query: RawQuery,
+    ) -> Result<RawContributions, GitHubError> {
+        let path = self.entry_path(client, user, organizations, query);
+        let mut entry = self.load(&path).await?;
+        let now = Utc::now();
+
+        let to_fetch = if self.offline {
+            entry.missing_ranges(date_range, |_, _| true)
+        } else {
+            entry.missing_ranges(date_range, |segment, date| {
+                now - segment.fetched_at < self.ttl
+                    || date + TimeDelta::days(SETTLED_AFTER_DAYS) <= segment.fetched_at.date_naive()
+            })
+        };
+
+        if self.offline {
+            if let Some(missing) = to_fetch.first() {
+                return Err(GitHubError::NotCached {
+                    user: user.to_string(),
+                    missing: *missing,
+                });
+            }
+        } else if !to_fetch.is_empty() {
+            // Ranges are downloaded one after another, each of them is
+            // already split in several concurrent queries
+            for range in to_fetch {
+                let contributions = fetch_raw_contributions(
+                    client,
+                    user,
+                    range,
+                    organizations,
+                    organization_ids,
+                    query,
+                )
+                .await?;
+                entry.insert(CachedSegment {
+                    range,
+                    fetched_at: now,
+                    contributions,
+                });
+            }
+            self.store(&path, &entry).await?;
+        }
+
+        Ok(entry.contributions(date_range))
+    }
+}
+
+/// Contributions downloaded at once for a range of days.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+struct CachedSegment {
+    range: DateRange,
+    fetched_at: DateTime<Utc>,
+    contributions: RawContributions,
+}
+
+/// Content of a cache file, segments never overlap.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+struct CacheEntry {
+    segments: Vec<CachedSegment>,
+}
+
+impl CacheEntry {
+    /// Ranges of days in `date_range` not covered by any segment for which
+    /// `is_valid` holds.
+    fn missing_ranges(
+        &self,
+        date_range: DateRange,
+        is_valid: impl Fn(&CachedSegment, NaiveDate) -> bool,
+    ) -> Vec<DateRange> {
+        let mut missing: Vec<DateRange> = Vec::new();
+        for date in date_range
+            .0
+            .iter_days()
+            .take_while(|date| *date <= date_range.1)
+        {
+            let cached = self.segments.iter().any(|segment| {
+                date >= segment.range.0 && date <= segment.range.1 && is_valid(segment, date)
+            });
+            if cached {
+                continue;
+            }
+            match missing.last_mut() {
+                Some(last) if last.1 + TimeDelta::days(1) == date => last.1 = date,
+                _ => missing.push((date, date)),
+            }
+        }
+        missing
+    }
+
+    /// Add a segment, replacing the days it covers in older segments.
+    fn insert(&mut self, new_segment: CachedSegment) {
+        let range = new_segment.range;
+        let mut segments = Vec::new();
+        for segment in self.segments.drain(..) {
+            let pieces = [
+                (
+                    segment.range.0,
+                    segment.range.1.min(range.0 - TimeDelta::days(1)),
+                ),
+                (
+                    segment.range.0.max(range.1 + TimeDelta::days(1)),
+                    segment.range.1,
+                ),
+            ];
+            for piece in pieces {
+                if piece.0 <= piece.1 {
+                    segments.push(CachedSegment {
+                        range: piece,
+                        fetched_at: segment.fetched_at,
+                        contributions: segment.contributions.slice(piece),
+                    });
+                }
+            }
+        }
+        segments.push(new_segment);
+        segments.sort_by_key(|segment| segment.range.0);
+        self.segments = segments;
+    }
+
+    /// Cached contributions made within `date_range`.
+    fn contributions(&self, date_range: DateRange) -> RawContributions {
+        let mut contributions = RawContributions::default();
+        for segment in &self.segments {
+            contributions.extend(segment.contributions.slice(date_range));
+        }
+        contributions
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::github::activity::RepositoryContributions;
+
+    fn date(month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2024, month, day).unwrap()
+    }
+
+    fn segment(range: DateRange, fetched_at: DateTime<Utc>) -> CachedSegment {
+        let calendar = range
+            .0
+            .iter_days()
+            .take_while(|date| *date <= range.1)
+            .map(|date| (date, 1))
+            .collect();
+        CachedSegment {
+            range,
+            fetched_at,
+            contributions: RawContributions {
+                calendar,
+                ..Default::default()
+            },
+        }
+    }
+
+    #[test]
+    fn test_missing_ranges() {
+        let entry = CacheEntry {
+            segments: vec![segment((date(1, 5), date(1, 10)), Utc::now())],
+        };
+        assert_eq!(
+            entry.missing_ranges((date(1, 1), date(1, 20)), |_, _| true),
+            vec![(date(1, 1), date(1, 4)), (date(1, 11), date(1, 20))]
+        );
+        assert_eq!(
+            entry.missing_ranges((date(1, 6), date(1, 8)), |_, _| true),
+            vec![]
+        );
+        assert_eq!(
+            entry.missing_ranges((date(1, 6), date(1, 8)), |_, day| day != date(1, 7)),
+            vec![(date(1, 7), date(1, 7))]
+        );
+    }
+
+    #[test]
+    fn test_insert_replaces_overlapping_days() {
+        let old = Utc::now() - TimeDelta::days(30);
+        let mut entry = CacheEntry {
+            segments: vec![segment((date(1, 1), date(1, 31)), old)],
+        };
+        let mut new_segment = segment((date(1, 10), date(1, 20)), Utc::now());
+        new_segment.contributions.calendar = vec![(date(1, 15), 7)];
+        entry.insert(new_segment);
+
+        let ranges: Vec<DateRange> = entry.segments.iter().map(|s| s.range).collect();
+        assert_eq!(
+            ranges,
+            vec![
+                (date(1, 1), date(1, 9)),
+                (date(1, 10), date(1, 20)),
+                (date(1, 21), date(1, 31))
+            ]
+        );
+
+        let contributions = entry.contributions((date(1, 1), date(1, 31)));
+        // 20 days from the old segment plus the only one of the new segment
+        assert_eq!(contributions.calendar.len(), 21);
+        assert!(contributions.calendar.contains(&(date(1, 15), 7)));
+    }
+
+    #[test]
+    fn test_cache_entry_round_trip() {
+        let mut cached = segment((date(2, 1), date(2, 2)), Utc::now());
+        cached.contributions.by_kind = vec![(
+            crate::github::activity::ContributionKind::Commit,
+            RepositoryContributions {
+                date: date(2, 1),
+                count: 3,
+                repository: "acme/flagship".to_string(),
+            },
+        )];
+        let entry = CacheEntry {
+            segments: vec![cached],
+        };
+        let json = serde_json::to_string(&entry).unwrap();
+        assert_eq!(serde_json::from_str::<CacheEntry>(&json).unwrap(), entry);
+    }
+
+    #[test]
+    fn test_entry_path_depends_on_token() {
+        let cache = ActivityCache::new(PathBuf::from("cache"));
+        let query = RawQuery {
+            calendar: true,
+            by_kind: false,
+        };
+        let path = |token: Option<&str>| {
+            let client = GitHubClient::new(token.map(str::to_string));
+            cache.entry_path(&client, "octocat", &[], query)
+        };
+
+        let public = path(None);
+        assert_eq!(
+            public,
+            PathBuf::from("cache/octocat.calendar@api.github.com.public.json")
+        );
+        let authenticated = path(Some("secret"));
+        assert_ne!(authenticated, public);
+        assert_ne!(authenticated, path(Some("other-secret")));
+        assert_eq!(authenticated, path(Some("secret")));
+        assert!(!authenticated.to_string_lossy().contains("secret"));
+    }
+
+    #[tokio::test]
+    async fn test_get_or_fetch_reuses_cached_days() {
+        use crate::github::client::GitHubEndpoint;
+        use wiremock::matchers::method;
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        let body = serde_json::json!({
+            "data": {"user": {"contributionsCollection": {
+                "restrictedContributionsCount": 0,
+                "hasAnyRestrictedContributions": false,
+                "contributionCalendar": {
+                "totalContributions": 2,
+                "weeks": [{"contributionDays": [
+                    {"date": "2024-01-02", "contributionCount": 2, "contributionLevel": "FOURTH_QUARTILE"}
+                ]}]
+            }}}}
+        });
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(body))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let dir = tempfile::tempdir().unwrap();
+        let client =
+            GitHubClient::new(None).with_endpoint(GitHubEndpoint::from_api_url(&server.uri()));
+        let query = RawQuery {
+            calendar: true,
+            by_kind: false,
+        };
+        let range = (date(1, 1), date(1, 7));
+
+        let cache = ActivityCache::new(dir.path().to_path_buf());
+        let fetched = cache
+            .get_or_fetch(&client, "octocat", range, &[], &HashMap::new(), query)
+            .await
+            .unwrap();
+        assert_eq!(fetched.calendar, vec![(date(1, 2), 2)]);
+
+        // Served from the cache, the mock server only expects one request
+        let offline = ActivityCache {
+            offline: true,
+            ..cache
+        };
+        let cached = offline
+            .get_or_fetch(&client, "octocat", range, &[], &HashMap::new(), query)
+            .await
+            .unwrap();
+        assert_eq!(cached, fetched);
+
+        let missing = offline
+            .get_or_fetch(
+                &client,
+                "octocat",
+                (date(1, 1), date(1, 9)),
+                &[],
+                &HashMap::new(),
+                query,
+            )
+            .await;
+        assert!(matches!(
+            missing,
+            Err(GitHubError::NotCached { missing, .. }) if missing == (date(1, 8), date(1, 9))
+        ));
+    }
+}
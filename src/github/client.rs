@@ -0,0 +1,447 @@
+use chrono::{DateTime, Utc};
+use reqwest::header::HeaderMap;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use crate::github::error::{GitHubError, GraphQLError};
+
+/// Base URL of the public GitHub REST API.
+pub const GITHUB_API_URL: &str = "https://api.github.com";
+
+/// URLs of the GitHub APIs to talk to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GitHubEndpoint {
+    /// URL GraphQL queries are posted to.
+    pub graphql_url: String,
+    /// Base URL of the REST API.
+    pub rest_url: String,
+}
+
+impl GitHubEndpoint {
+    /// Derive the GraphQL endpoint from the base URL of the REST API, following
+    /// the convention of the `GITHUB_API_URL` variable set by GitHub Actions:
+    /// `https://api.github.com` for github.com and `https://HOST/api/v3` for
+    /// GitHub Enterprise Server, whose GraphQL endpoint is `https://HOST/api/graphql`.
+    /// Other URLs, such as local mock servers, get `/graphql` appended.
+    pub fn from_api_url(api_url: &str) -> Self {
+        let rest_url = api_url.trim_end_matches('/').to_string();
+        let graphql_url = match rest_url.strip_suffix("/v3") {
+            Some(api_root) if api_root.ends_with("/api") => format!("{}/graphql", api_root),
+            _ => format!("{}/graphql", rest_url),
+        };
+        GitHubEndpoint {
+            graphql_url,
+            rest_url,
+        }
+    }
+}
+
+impl Default for GitHubEndpoint {
+    fn default() -> Self {
+        GitHubEndpoint::from_api_url(GITHUB_API_URL)
+    }
+}
+
+/// Policy used to retry requests failing because of transient network
+/// errors, GitHub server errors or rate limiting.
+/// The wait before the n-th retry is `initial_backoff * 2^(n-1)`, capped at
+/// `max_backoff`. Rate limited requests wait until the reset time announced by
+/// GitHub instead, unless it is further away than `max_backoff`, in which case
+/// the error is returned to the caller.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RetryPolicy {
+    pub max_retries: u32,
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+}
+
+impl RetryPolicy {
+    /// Policy failing on the first error.
+    pub fn none() -> Self {
+        RetryPolicy {
+            max_retries: 0,
+            ..Default::default()
+        }
+    }
+
+    fn backoff(&self, retry: u32) -> Duration {
+        self.initial_backoff
+            .saturating_mul(2u32.saturating_pow(retry.saturating_sub(1)))
+            .min(self.max_backoff)
+    }
+
+    /// How long to wait before the given retry (starting at 1) of a request
+    /// which failed with `error`, `None` if it should not be retried.
+    fn wait_before_retry(&self, retry: u32, error: &GitHubError) -> Option<Duration> {
+        if retry > self.max_retries {
+            return None;
+        }
+        let backoff = self.backoff(retry);
+        match error {
+            GitHubError::Network(error) if error.is_timeout() || error.is_connect() => {
+                Some(backoff)
+            }
+            GitHubError::Http { status, .. } if status.is_server_error() => Some(backoff),
+            GitHubError::RateLimited { reset_at } => {
+                let until_reset = reset_at
+                    .and_then(|reset_at| (reset_at - Utc::now()).to_std().ok())
+                    .unwrap_or_default();
+                let wait = backoff.max(until_reset);
+                (wait <= self.max_backoff).then_some(wait)
+            }
+            _ => None,
+        }
+    }
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            max_retries: 3,
+            initial_backoff: Duration::from_secs(1),
+            max_backoff: Duration::from_secs(60),
+        }
+    }
+}
+
+/// Rate limit budget reported by GitHub in the `x-ratelimit-*` headers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RateLimit {
+    pub limit: Option<u32>,
+    pub remaining: u32,
+    pub reset_at: DateTime<Utc>,
+}
+
+impl RateLimit {
+    fn from_headers(headers: &HeaderMap) -> Option<Self> {
+        let header = |name: &str| {
+            headers
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .and_then(|value| value.parse::<i64>().ok())
+        };
+        Some(RateLimit {
+            limit: header("x-ratelimit-limit").map(|limit| limit as u32),
+            remaining: header("x-ratelimit-remaining")? as u32,
+            reset_at: DateTime::from_timestamp(header("x-ratelimit-reset")?, 0)?,
+        })
+    }
+}
+
+/// Client used to query GitHub APIs.
+/// If `maybe_token` is not `None`, it will be used as application
+/// authentication token. This is required to obtain private repositories
+/// contributions.
+/// Clones share the last seen rate limit budget.
+#[derive(Debug, Clone)]
+pub struct GitHubClient {
+    http: reqwest::Client,
+    endpoint: GitHubEndpoint,
+    maybe_token: Option<String>,
+    retry_policy: RetryPolicy,
+    rate_limit: Arc<Mutex<Option<RateLimit>>>,
+}
+
+impl GitHubClient {
+    /// Client for the public GitHub API.
+    pub fn new(maybe_token: Option<String>) -> Self {
+        GitHubClient {
+            http: reqwest::Client::new(),
+            endpoint: GitHubEndpoint::default(),
+            maybe_token,
+            retry_policy: RetryPolicy::default(),
+            rate_limit: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    /// Point the client to other API endpoints, e.g: a GitHub Enterprise Server.
+    pub fn with_endpoint(mut self, endpoint: GitHubEndpoint) -> Self {
+        self.endpoint = endpoint;
+        self
+    }
+
+    pub fn with_retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.retry_policy = retry_policy;
+        self
+    }
+
+    pub fn endpoint(&self) -> &GitHubEndpoint {
+        &self.endpoint
+    }
+
+    /// Short hash of the token telling clients with different tokens
+    /// apart without revealing them, `None` for anonymous clients.
+    pub(crate) fn token_fingerprint(&self) -> Option<String> {
+        self.maybe_token.as_ref().map(|token| {
+            Sha256::digest(token.as_bytes())
+                .iter()
+                .take(8)
+                .map(|byte| format!("{:02x}", byte))
+                .collect()
+        })
+    }
+
+    /// Rate limit budget reported by GitHub in the last response, if any.
+    /// Batch jobs can use it to pace their requests.
+    pub fn rate_limit(&self) -> Option<RateLimit> {
+        *self.rate_limit.lock().unwrap()
+    }
+
+    /// Send a GraphQL query to GitHub and return the `data` of the response,
+    /// retrying according to the client [`RetryPolicy`].
+    pub(crate) async fn post_graphql<V: Serialize, T: DeserializeOwned>(
+        &self,
+        graphql_query: &str,
+        variables: V,
+    ) -> Result<T, GitHubError> {
+        let request_body = GraphQLRequest {
+            query: graphql_query.to_string(),
+            variables,
+        };
+
+        let mut retry = 0;
+        loop {
+            match self.post_graphql_once(&request_body).await {
+                Err(error) => {
+                    retry += 1;
+                    match self.retry_policy.wait_before_retry(retry, &error) {
+                        Some(wait) => tokio::time::sleep(wait).await,
+                        None => return Err(error),
+                    }
+                }
+                result => return result,
+            }
+        }
+    }
+
+    async fn post_graphql_once<V: Serialize, T: DeserializeOwned>(
+        &self,
+        request_body: &GraphQLRequest<V>,
+    ) -> Result<T, GitHubError> {
+        // Build the request
+        let mut request = self
+            .http
+            .post(&self.endpoint.graphql_url)
+            .header("User-Agent", "gh-trophy")
+            .json(request_body);
+
+        // Add authentication if token is provided
+        if let Some(token) = &self.maybe_token {
+            request = request.bearer_auth(token);
+        }
+
+        // Make the request
+        let response = request.send().await?;
+
+        // Check for errors
+        let status = response.status();
+        let headers = response.headers().clone();
+        let response_text = response.text().await?;
+
+        let maybe_rate_limit = RateLimit::from_headers(&headers);
+        if maybe_rate_limit.is_some() {
+            *self.rate_limit.lock().unwrap() = maybe_rate_limit;
+        }
+
+        if !status.is_success() {
+            return Err(GitHubError::from_response(status, &headers, response_text));
+        }
+
+        // Parse the GraphQL response, errors are checked before decoding
+        // `data` as it may be incomplete when the query failed.
+        let graphql_response: GraphQLResponse = serde_json::from_str(&response_text)?;
+
+        if graphql_response
+            .errors
+            .iter()
+            .any(|error| error.error_type.as_deref() == Some("RATE_LIMITED"))
+        {
+            return Err(GitHubError::RateLimited {
+                reset_at: maybe_rate_limit.map(|rate_limit| rate_limit.reset_at),
+            });
+        }
+        if !graphql_response.errors.is_empty() {
+            return Err(GitHubError::GraphQL(graphql_response.errors));
+        }
+
+        Ok(serde_json::from_value(graphql_response.data)?)
+    }
+}
+
+// GraphQL request and response envelopes
+#[derive(Serialize, Debug)]
+struct GraphQLRequest<V> {
+    query: String,
+    variables: V,
+}
+
+#[derive(Deserialize, Debug)]
+struct GraphQLResponse {
+    #[serde(default)]
+    data: serde_json::Value,
+    #[serde(default)]
+    errors: Vec<GraphQLError>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_endpoint_github_com() {
+        let endpoint = GitHubEndpoint::default();
+        assert_eq!(endpoint.rest_url, "https://api.github.com");
+        assert_eq!(endpoint.graphql_url, "https://api.github.com/graphql");
+    }
+
+    #[test]
+    fn test_endpoint_enterprise_server() {
+        let endpoint = GitHubEndpoint::from_api_url("https://ghe.example.com/api/v3/");
+        assert_eq!(endpoint.rest_url, "https://ghe.example.com/api/v3");
+        assert_eq!(endpoint.graphql_url, "https://ghe.example.com/api/graphql");
+    }
+
+    #[test]
+    fn test_endpoint_other_url() {
+        let endpoint = GitHubEndpoint::from_api_url("http://127.0.0.1:8080");
+        assert_eq!(endpoint.rest_url, "http://127.0.0.1:8080");
+        assert_eq!(endpoint.graphql_url, "http://127.0.0.1:8080/graphql");
+    }
+
+    async fn post_to_mock(
+        response: wiremock::ResponseTemplate,
+    ) -> Result<serde_json::Value, GitHubError> {
+        use wiremock::matchers::method;
+        use wiremock::{Mock, MockServer};
+
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(response)
+            .mount(&server)
+            .await;
+        let client =
+            GitHubClient::new(None).with_endpoint(GitHubEndpoint::from_api_url(&server.uri()));
+        client
+            .post_graphql("query { viewer { login } }", serde_json::json!({}))
+            .await
+    }
+
+    #[tokio::test]
+    async fn test_post_graphql_unauthorized() {
+        let result =
+            post_to_mock(wiremock::ResponseTemplate::new(401).set_body_string("Bad credentials"))
+                .await;
+        assert!(matches!(result, Err(GitHubError::Unauthorized { .. })));
+    }
+
+    #[tokio::test]
+    async fn test_post_graphql_errors() {
+        let body = serde_json::json!({
+            "data": null,
+            "errors": [{"message": "Field 'foo' doesn't exist"}]
+        });
+        let result = post_to_mock(wiremock::ResponseTemplate::new(200).set_body_json(body)).await;
+        match result {
+            Err(GitHubError::GraphQL(errors)) => {
+                assert_eq!(errors[0].message, "Field 'foo' doesn't exist")
+            }
+            other => panic!("unexpected result {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_retry_policy_backoff() {
+        let policy = RetryPolicy {
+            max_retries: 10,
+            initial_backoff: Duration::from_secs(1),
+            max_backoff: Duration::from_secs(5),
+        };
+        assert_eq!(policy.backoff(1), Duration::from_secs(1));
+        assert_eq!(policy.backoff(2), Duration::from_secs(2));
+        assert_eq!(policy.backoff(3), Duration::from_secs(4));
+        assert_eq!(policy.backoff(4), Duration::from_secs(5));
+    }
+
+    #[test]
+    fn test_retry_policy_wait_before_retry() {
+        let policy = RetryPolicy::default();
+        let server_error = GitHubError::Http {
+            status: reqwest::StatusCode::BAD_GATEWAY,
+            body: String::new(),
+        };
+        assert_eq!(
+            policy.wait_before_retry(1, &server_error),
+            Some(Duration::from_secs(1))
+        );
+        assert_eq!(policy.wait_before_retry(4, &server_error), None);
+        assert_eq!(
+            RetryPolicy::none().wait_before_retry(1, &server_error),
+            None
+        );
+
+        let unauthorized = GitHubError::Unauthorized {
+            body: String::new(),
+        };
+        assert_eq!(policy.wait_before_retry(1, &unauthorized), None);
+
+        let soon = GitHubError::RateLimited {
+            reset_at: Some(Utc::now() + chrono::Duration::seconds(30)),
+        };
+        let wait = policy.wait_before_retry(1, &soon).unwrap();
+        assert!(wait > Duration::from_secs(20) && wait <= Duration::from_secs(30));
+
+        let later = GitHubError::RateLimited {
+            reset_at: Some(Utc::now() + chrono::Duration::hours(1)),
+        };
+        assert_eq!(policy.wait_before_retry(1, &later), None);
+    }
+
+    #[tokio::test]
+    async fn test_post_graphql_retries_server_errors() {
+        use wiremock::matchers::method;
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(502))
+            .up_to_n_times(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .insert_header("x-ratelimit-limit", "5000")
+                    .insert_header("x-ratelimit-remaining", "4321")
+                    .insert_header("x-ratelimit-reset", "1700000000")
+                    .set_body_json(serde_json::json!({"data": {"answer": 42}})),
+            )
+            .mount(&server)
+            .await;
+
+        let client = GitHubClient::new(None)
+            .with_endpoint(GitHubEndpoint::from_api_url(&server.uri()))
+            .with_retry_policy(RetryPolicy {
+                max_retries: 1,
+                initial_backoff: Duration::from_millis(1),
+                max_backoff: Duration::from_millis(10),
+            });
+        assert_eq!(client.rate_limit(), None);
+
+        let data: serde_json::Value = client
+            .post_graphql("query { answer }", serde_json::json!({}))
+            .await
+            .unwrap();
+        assert_eq!(data["answer"], 42);
+        assert_eq!(
+            client.rate_limit(),
+            Some(RateLimit {
+                limit: Some(5000),
+                remaining: 4321,
+                reset_at: DateTime::from_timestamp(1700000000, 0).unwrap(),
+            })
+        );
+    }
+}
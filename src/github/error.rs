@@ -0,0 +1,177 @@
+use chrono::{DateTime, NaiveDate, Utc};
+use reqwest::StatusCode;
+use reqwest::header::HeaderMap;
+use serde::Deserialize;
+
+/// Error returned by GitHub in the `errors` array of a GraphQL response.
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct GraphQLError {
+    pub message: String,
+    /// GitHub classification of the error, e.g: `NOT_FOUND` or `RATE_LIMITED`.
+    #[serde(rename = "type")]
+    pub error_type: Option<String>,
+    /// Path of the response field the error refers to.
+    #[serde(default)]
+    pub path: Vec<serde_json::Value>,
+}
+
+impl GraphQLError {
+    /// Whether the error reports that the object requested in the
+    /// top-level `field` of the query does not exist.
+    pub fn is_not_found(&self, field: &str) -> bool {
+        self.error_type.as_deref() == Some("NOT_FOUND")
+            && self.path.first().and_then(|segment| segment.as_str()) == Some(field)
+    }
+}
+
+impl std::fmt::Display for GraphQLError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.error_type {
+            Some(error_type) => write!(f, "{} ({})", self.message, error_type),
+            None => write!(f, "{}", self.message),
+        }
+    }
+}
+
+/// Errors raised while downloading data from GitHub.
+#[derive(thiserror::Error, Debug)]
+pub enum GitHubError {
+    /// The request could not be sent or its response could not be read.
+    #[error("network error: {0}")]
+    Network(#[from] reqwest::Error),
+    /// GitHub answered with an unexpected HTTP status.
+    #[error("GitHub API error {status}: {body}")]
+    Http { status: StatusCode, body: String },
+    /// The rate limit budget is exhausted, `reset_at` tells when it is
+    /// worth trying again if GitHub reported it.
+    #[error("GitHub API rate limit exceeded{}", .reset_at.map(|reset_at| format!(", resets at {}", reset_at)).unwrap_or_default())]
+    RateLimited { reset_at: Option<DateTime<Utc>> },
+    /// The token is missing, invalid or lacks permissions.
+    #[error("GitHub API authentication failed: {body}")]
+    Unauthorized { body: String },
+    /// The requested user does not exist.
+    #[error("GitHub user not found: {0}")]
+    UserNotFound(String),
+    /// The requested organization does not exist.
+    #[error("GitHub organization not found: {0}")]
+    OrganizationNotFound(String),
+    /// The GraphQL query was rejected.
+    #[error("GitHub GraphQL query error: {}", .0.iter().map(|error| error.to_string()).collect::<Vec<_>>().join("; "))]
+    GraphQL(Vec<GraphQLError>),
+    /// The response body, or a cache file, is not what was expected.
+    #[error("unexpected GitHub API response: {0}")]
+    Decode(#[from] serde_json::Error),
+    /// The activity cache could not be read or written.
+    #[error("activity cache error: {0}")]
+    Cache(std::io::Error),
+    /// Offline mode was requested but some days are not in the cache.
+    #[error("activity of {user} from {} to {} is not cached", .missing.0, .missing.1)]
+    NotCached {
+        user: String,
+        missing: (NaiveDate, NaiveDate),
+    },
+}
+
+impl GitHubError {
+    /// Classify an unsuccessful HTTP response.
+    pub(crate) fn from_response(status: StatusCode, headers: &HeaderMap, body: String) -> Self {
+        let header = |name: &str| {
+            headers
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .and_then(|value| value.parse::<i64>().ok())
+        };
+
+        let rate_limited = status == StatusCode::TOO_MANY_REQUESTS
+            || (status == StatusCode::FORBIDDEN
+                && (header("x-ratelimit-remaining") == Some(0) || header("retry-after").is_some()));
+
+        if rate_limited {
+            let reset_at = match header("retry-after") {
+                Some(seconds) => Some(Utc::now() + chrono::Duration::seconds(seconds)),
+                None => header("x-ratelimit-reset")
+                    .and_then(|timestamp| DateTime::from_timestamp(timestamp, 0)),
+            };
+            GitHubError::RateLimited { reset_at }
+        } else if status == StatusCode::UNAUTHORIZED {
+            GitHubError::Unauthorized { body }
+        } else {
+            GitHubError::Http { status, body }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::header::HeaderValue;
+
+    #[test]
+    fn test_from_response_unauthorized() {
+        let error = GitHubError::from_response(
+            StatusCode::UNAUTHORIZED,
+            &HeaderMap::new(),
+            "Bad credentials".to_string(),
+        );
+        assert!(matches!(error, GitHubError::Unauthorized { body } if body == "Bad credentials"));
+    }
+
+    #[test]
+    fn test_from_response_rate_limited() {
+        let mut headers = HeaderMap::new();
+        headers.insert("x-ratelimit-remaining", HeaderValue::from_static("0"));
+        headers.insert("x-ratelimit-reset", HeaderValue::from_static("1700000000"));
+        let error = GitHubError::from_response(StatusCode::FORBIDDEN, &headers, String::new());
+        assert!(matches!(
+            error,
+            GitHubError::RateLimited { reset_at: Some(reset_at) } if reset_at.timestamp() == 1700000000
+        ));
+    }
+
+    #[test]
+    fn test_from_response_forbidden() {
+        let mut headers = HeaderMap::new();
+        headers.insert("x-ratelimit-remaining", HeaderValue::from_static("4999"));
+        let error = GitHubError::from_response(StatusCode::FORBIDDEN, &headers, "nope".to_string());
+        assert!(matches!(
+            error,
+            GitHubError::Http {
+                status: StatusCode::FORBIDDEN,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn test_graphql_error_display() {
+        let error = GitHubError::GraphQL(vec![
+            GraphQLError {
+                message: "first".to_string(),
+                error_type: None,
+                path: vec![],
+            },
+            GraphQLError {
+                message: "second".to_string(),
+                error_type: Some("FORBIDDEN".to_string()),
+                path: vec![],
+            },
+        ]);
+        assert_eq!(
+            error.to_string(),
+            "GitHub GraphQL query error: first; second (FORBIDDEN)"
+        );
+    }
+
+    #[test]
+    fn test_graphql_error_is_not_found() {
+        let json = r#"{
+            "type": "NOT_FOUND",
+            "path": ["user"],
+            "locations": [{"line": 3, "column": 13}],
+            "message": "Could not resolve to a User with the login of 'nobody'."
+        }"#;
+        let error: GraphQLError = serde_json::from_str(json).unwrap();
+        assert!(error.is_not_found("user"));
+        assert!(!error.is_not_found("organization"));
+    }
+}
@@ -0,0 +1,421 @@
+use futures::StreamExt;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+use crate::github::activity::{
+    Activity, ActivityOptions, DateRange, MergeMode, PageInfo, get_activity,
+};
+use crate::github::client::GitHubClient;
+use crate::github::error::GitHubError;
+
+/// Number of members requested per page.
+const MEMBERS_PAGE_SIZE: usize = 100;
+
+#[derive(Serialize, Debug)]
+struct OrganizationVariables {
+    login: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    after: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+struct OrganizationData<T> {
+    organization: Option<T>,
+}
+
+#[derive(Deserialize, Debug)]
+struct OrganizationId {
+    id: String,
+}
+
+#[derive(Deserialize, Debug)]
+struct OrganizationMembers {
+    #[serde(rename = "membersWithRole")]
+    members_with_role: MembersConnection,
+}
+
+#[derive(Deserialize, Debug)]
+struct MembersConnection {
+    #[serde(rename = "pageInfo")]
+    page_info: PageInfo,
+    nodes: Vec<Member>,
+}
+
+#[derive(Deserialize, Debug)]
+struct Member {
+    login: String,
+}
+
+/// Send a query about the organization `login`, failing with
+/// [`GitHubError::OrganizationNotFound`] if it does not exist.
+async fn query_organization<T: DeserializeOwned>(
+    client: &GitHubClient,
+    graphql_query: &str,
+    login: &str,
+    after: Option<String>,
+) -> Result<T, GitHubError> {
+    let variables = OrganizationVariables {
+        login: login.to_string(),
+        after,
+    };
+
+    let organization_not_found = || GitHubError::OrganizationNotFound(login.to_string());
+
+    let data: OrganizationData<T> = client
+        .post_graphql(graphql_query, variables)
+        .await
+        .map_err(|error| match error {
+            GitHubError::GraphQL(errors)
+                if errors
+                    .iter()
+                    .any(|error| error.is_not_found("organization")) =>
+            {
+                organization_not_found()
+            }
+            error => error,
+        })?;
+
+    data.organization.ok_or_else(organization_not_found)
+}
+
+/// Obtain the GraphQL node id of an organization from its login.
+pub(crate) async fn fetch_organization_id(
+    client: &GitHubClient,
+    login: &str,
+) -> Result<String, GitHubError> {
+    let graphql_query = r#"
+        query($login: String!) {
+            organization(login: $login) {
+                id
+            }
+        }
+    "#;
+
+    let organization: OrganizationId =
+        query_organization(client, graphql_query, login, None).await?;
+
+    Ok(organization.id)
+}
+
+/// Handles of the members of the organization `login`.
+/// Only public members are listed unless `client` authenticates as a
+/// member of the organization.
+pub async fn fetch_organization_members(
+    client: &GitHubClient,
+    login: &str,
+) -> Result<Vec<String>, GitHubError> {
+    let graphql_query = format!(
+        r#"
+        query($login: String!, $after: String) {{
+            organization(login: $login) {{
+                membersWithRole(first: {}, after: $after) {{
+                    pageInfo {{
+                        hasNextPage
+                        endCursor
+                    }}
+                    nodes {{
+                        login
+                    }}
+                }}
+            }}
+        }}
+    "#,
+        MEMBERS_PAGE_SIZE
+    );
+
+    let mut members = Vec::new();
+    let mut after = None;
+    loop {
+        let organization: OrganizationMembers =
+            query_organization(client, &graphql_query, login, after).await?;
+        let connection = organization.members_with_role;
+        members.extend(connection.nodes.into_iter().map(|member| member.login));
+        if !connection.page_info.has_next_page {
+            break;
+        }
+        after = connection.page_info.end_cursor;
+    }
+    Ok(members)
+}
+
+/// Combined activity of the members of an organization.
+#[derive(Debug)]
+pub struct OrganizationActivity {
+    /// Handles of the members whose activity was downloaded.
+    pub members: Vec<String>,
+    pub activity: Activity,
+    /// Members whose activity could not be downloaded, e.g: suspended
+    /// accounts, with the error obtained.
+    pub failed_members: Vec<(String, GitHubError)>,
+}
+
+/// Download the activity of every member of the organization `login`,
+/// only counting their contributions to the organization, as well as
+/// to the organizations already included by `options`, and combine it
+/// into a single [`Activity`].
+/// At most `parallelism` members are downloaded at once. Members whose
+/// activity can not be downloaded are skipped and reported, this only
+/// fails if no member could be downloaded.
+pub async fn get_organization_activity(
+    client: &GitHubClient,
+    login: &str,
+    date_range: DateRange,
+    options: &ActivityOptions,
+    merge: MergeMode,
+    parallelism: usize,
+) -> Result<OrganizationActivity, GitHubError> {
+    let all_members = fetch_organization_members(client, login).await?;
+
+    // Node ids are resolved once instead of once per member
+    let mut options = options.clone();
+    let organizations = &mut options.filters.include_organizations;
+    if !organizations
+        .iter()
+        .any(|organization| organization.eq_ignore_ascii_case(login))
+    {
+        organizations.push(login.to_string());
+    }
+    for organization in organizations.iter() {
+        if !options.organization_ids.contains_key(organization) {
+            let id = fetch_organization_id(client, organization).await?;
+            options.organization_ids.insert(organization.clone(), id);
+        }
+    }
+
+    let results: Vec<Result<Activity, GitHubError>> = futures::stream::iter(&all_members)
+        .map(|member| get_activity(client, member, date_range, &options))
+        .buffered(parallelism.max(1))
+        .collect()
+        .await;
+
+    let mut members = Vec::new();
+    let mut activities = Vec::new();
+    let mut failed_members = Vec::new();
+    for (member, result) in all_members.into_iter().zip(results) {
+        match result {
+            Ok(activity) => {
+                members.push(member);
+                activities.push(activity);
+            }
+            Err(error) => failed_members.push((member, error)),
+        }
+    }
+    if members.is_empty()
+        && let Some((_, error)) = failed_members.pop()
+    {
+        return Err(error);
+    }
+
+    let activity = Activity::merge(&activities, merge)
+        .unwrap_or_else(|| Activity::from_daily_contributions(date_range, vec![]));
+
+    Ok(OrganizationActivity {
+        members,
+        activity,
+        failed_members,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::github::client::GitHubEndpoint;
+    use chrono::NaiveDate;
+    use wiremock::matchers::{body_partial_json, body_string_contains, method};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    #[tokio::test]
+    async fn test_fetch_organization_members_pages() {
+        let server = MockServer::start().await;
+        let page = |logins: &[&str], next: Option<&str>| {
+            serde_json::json!({"data": {"organization": {"membersWithRole": {
+                "pageInfo": {"hasNextPage": next.is_some(), "endCursor": next},
+                "nodes": logins.iter().map(|login| serde_json::json!({"login": login})).collect::<Vec<_>>()
+            }}}})
+        };
+        Mock::given(method("POST"))
+            .and(body_partial_json(
+                serde_json::json!({"variables": {"after": "cursor"}}),
+            ))
+            .respond_with(ResponseTemplate::new(200).set_body_json(page(&["carol"], None)))
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .respond_with(
+                ResponseTemplate::new(200).set_body_json(page(&["alice", "bob"], Some("cursor"))),
+            )
+            .mount(&server)
+            .await;
+
+        let client =
+            GitHubClient::new(None).with_endpoint(GitHubEndpoint::from_api_url(&server.uri()));
+        let members = fetch_organization_members(&client, "acme").await.unwrap();
+
+        assert_eq!(members, vec!["alice", "bob", "carol"]);
+    }
+
+    #[tokio::test]
+    async fn test_organization_not_found() {
+        let server = MockServer::start().await;
+        let body = serde_json::json!({
+            "data": {"organization": null},
+            "errors": [{
+                "type": "NOT_FOUND",
+                "path": ["organization"],
+                "message": "Could not resolve to an Organization with the login of 'nope'."
+            }]
+        });
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(body))
+            .mount(&server)
+            .await;
+
+        let client =
+            GitHubClient::new(None).with_endpoint(GitHubEndpoint::from_api_url(&server.uri()));
+        let result = fetch_organization_members(&client, "nope").await;
+
+        assert!(matches!(result, Err(GitHubError::OrganizationNotFound(login)) if login == "nope"));
+    }
+
+    #[tokio::test]
+    async fn test_get_organization_activity() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(body_string_contains("membersWithRole"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!(
+                {"data": {"organization": {"membersWithRole": {
+                    "pageInfo": {"hasNextPage": false, "endCursor": null},
+                    "nodes": [{"login": "alice"}, {"login": "ghost"}, {"login": "bob"}]
+                }}}}
+            )))
+            .mount(&server)
+            .await;
+        // Node ids are resolved once for all the members
+        for login in ["acme", "labs"] {
+            Mock::given(method("POST"))
+                .and(body_string_contains("id"))
+                .and(body_partial_json(
+                    serde_json::json!({"variables": {"login": login}}),
+                ))
+                .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!(
+                    {"data": {"organization": {"id": format!("O_{}", login)}}}
+                )))
+                .expect(1)
+                .mount(&server)
+                .await;
+        }
+        // Suspended or deleted accounts are skipped
+        Mock::given(method("POST"))
+            .and(body_partial_json(
+                serde_json::json!({"variables": {"username": "ghost"}}),
+            ))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "data": {"user": null},
+                "errors": [{
+                    "type": "NOT_FOUND",
+                    "path": ["user"],
+                    "message": "Could not resolve to a User with the login of 'ghost'."
+                }]
+            })))
+            .with_priority(1)
+            .mount(&server)
+            .await;
+        // Contributions are only requested to the included organizations
+        for organization_id in ["O_acme", "O_labs"] {
+            Mock::given(method("POST"))
+                .and(body_partial_json(
+                    serde_json::json!({"variables": {"organizationID": organization_id}}),
+                ))
+                .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!(
+                    {"data": {"user": {"contributionsCollection": {
+                    "restrictedContributionsCount": 0,
+                    "hasAnyRestrictedContributions": false,
+                    "contributionCalendar": {
+                        "totalContributions": 1,
+                        "weeks": [{"contributionDays": [
+                            {"date": "2024-01-02", "contributionCount": 1, "contributionLevel": "FOURTH_QUARTILE"}
+                        ]}]
+                    }}}}}
+                )))
+                .mount(&server)
+                .await;
+        }
+
+        let client =
+            GitHubClient::new(None).with_endpoint(GitHubEndpoint::from_api_url(&server.uri()));
+        let start = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+        let end = NaiveDate::from_ymd_opt(2024, 1, 7).unwrap();
+        let mut options = ActivityOptions::default();
+        options.filters.include_organizations = vec!["labs".to_string()];
+        let organization_activity =
+            get_organization_activity(&client, "acme", (start, end), &options, MergeMode::Sum, 2)
+                .await
+                .unwrap();
+
+        assert_eq!(organization_activity.members, vec!["alice", "bob"]);
+        assert!(matches!(
+            organization_activity.failed_members.as_slice(),
+            [(member, GitHubError::UserNotFound(_))] if member == "ghost"
+        ));
+        // Contributions to both organizations are counted
+        assert_eq!(
+            organization_activity
+                .activity
+                .contributions_on(NaiveDate::from_ymd_opt(2024, 1, 2).unwrap()),
+            4
+        );
+    }
+
+    #[tokio::test]
+    async fn test_get_organization_activity_without_members() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(body_string_contains("membersWithRole"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!(
+                {"data": {"organization": {"membersWithRole": {
+                    "pageInfo": {"hasNextPage": false, "endCursor": null},
+                    "nodes": [{"login": "ghost"}]
+                }}}}
+            )))
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(body_string_contains("id"))
+            .and(body_partial_json(
+                serde_json::json!({"variables": {"login": "acme"}}),
+            ))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!(
+                {"data": {"organization": {"id": "O_acme"}}}
+            )))
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "data": {"user": null},
+                "errors": [{
+                    "type": "NOT_FOUND",
+                    "path": ["user"],
+                    "message": "Could not resolve to a User with the login of 'ghost'."
+                }]
+            })))
+            .mount(&server)
+            .await;
+
+        let client =
+            GitHubClient::new(None).with_endpoint(GitHubEndpoint::from_api_url(&server.uri()));
+        let start = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+        let end = NaiveDate::from_ymd_opt(2024, 1, 7).unwrap();
+        let result = get_organization_activity(
+            &client,
+            "acme",
+            (start, end),
+            &Default::default(),
+            MergeMode::Sum,
+            2,
+        )
+        .await;
+
+        // With nobody to skip to, the error of the last member is returned
+        assert!(matches!(result, Err(GitHubError::UserNotFound(user)) if user == "ghost"));
+    }
+}
@@ -0,0 +1,254 @@
+/// Function mapping the values of the activity matrices, contribution
+/// counts or levels, to pillar heights.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum HeightScale {
+    /// Heights proportional to the values.
+    Linear,
+    /// Heights proportional to `ln(1 + value)`, zero values get the
+    /// minimum height.
+    #[default]
+    Log1p,
+    /// Heights proportional to the square root of the values.
+    Sqrt,
+    /// Heights proportional to the values, capped at the given value so
+    /// that outliers get the maximum height.
+    Clamp(u32),
+    /// Heights proportional to the fraction of non zero values lower or
+    /// equal to each value, evenly spreading the busiest and quietest days.
+    Percentile,
+}
+
+/// Range of heights, in millimetres, values are mapped to.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct HeightOptions {
+    pub scale: HeightScale,
+    min_height: f64,
+    max_height: f64,
+}
+
+/// Errors raised by invalid height ranges.
+#[derive(thiserror::Error, Debug, Clone, PartialEq)]
+pub enum HeightError {
+    #[error("pillar heights must be finite and not negative")]
+    Invalid,
+    #[error("the minimum height {min} is greater than the maximum height {max}")]
+    Inverted { min: f64, max: f64 },
+}
+
+impl HeightOptions {
+    /// Map values with `scale` to heights from `min_height` to
+    /// `max_height`, both finite, not negative and in order.
+    pub fn new(scale: HeightScale, min_height: f64, max_height: f64) -> Result<Self, HeightError> {
+        if !(min_height >= 0.0 && min_height.is_finite() && max_height.is_finite()) {
+            return Err(HeightError::Invalid);
+        }
+        if min_height > max_height {
+            return Err(HeightError::Inverted {
+                min: min_height,
+                max: max_height,
+            });
+        }
+        Ok(HeightOptions {
+            scale,
+            min_height,
+            max_height,
+        })
+    }
+
+    /// Height of the pillars of days without contributions.
+    pub fn min_height(&self) -> f64 {
+        self.min_height
+    }
+
+    /// Height of the pillars of the busiest days.
+    pub fn max_height(&self) -> f64 {
+        self.max_height
+    }
+}
+
+impl Default for HeightOptions {
+    fn default() -> Self {
+        HeightOptions {
+            scale: HeightScale::default(),
+            min_height: 2.0,
+            max_height: 30.0,
+        }
+    }
+}
+
+/// Heights are rounded to hundredths of millimetre.
+fn round_height(height: f64) -> f64 {
+    (height * 100.0).round() / 100.0
+}
+
+/// Height scale fitted to the values of a reference matrix.
+#[derive(Debug, Clone)]
+pub struct HeightMapping {
+    options: HeightOptions,
+    /// Non zero values of the reference matrix, sorted.
+    values: Vec<u32>,
+}
+
+impl HeightMapping {
+    /// Fit `options` to the values of `reference`, the highest of them
+    /// getting the maximum height.
+    pub fn fit(options: HeightOptions, reference: &[Vec<Option<u32>>]) -> Self {
+        let mut values: Vec<u32> = reference
+            .iter()
+            .flatten()
+            .flatten()
+            .copied()
+            .filter(|value| *value > 0)
+            .collect();
+        values.sort_unstable();
+        HeightMapping { options, values }
+    }
+
+    /// Fraction of the height range, from 0 to 1, `value` is mapped to.
+    fn fraction(&self, value: u32) -> f64 {
+        let max = self.values.last().copied().unwrap_or(0);
+        if value == 0 || max == 0 {
+            return 0.0;
+        }
+        let value = value as f64;
+        let max = max as f64;
+        let fraction = match self.options.scale {
+            HeightScale::Linear => value / max,
+            HeightScale::Log1p => value.ln_1p() / max.ln_1p(),
+            HeightScale::Sqrt => value.sqrt() / max.sqrt(),
+            HeightScale::Clamp(cap) => {
+                // A cap of 0 would divide by 0, every value is over a cap of 1
+                let cap = cap.max(1) as f64;
+                value.min(cap) / max.min(cap)
+            }
+            HeightScale::Percentile => {
+                let lower_or_equal = self.values.partition_point(|other| *other as f64 <= value);
+                lower_or_equal as f64 / self.values.len() as f64
+            }
+        };
+        fraction.clamp(0.0, 1.0)
+    }
+
+    /// Height, in millimetres, of the pillar representing `value`.
+    pub fn height(&self, value: u32) -> f64 {
+        let HeightOptions {
+            min_height,
+            max_height,
+            ..
+        } = self.options;
+        round_height(min_height + (max_height - min_height) * self.fraction(value))
+    }
+
+    /// Replace every value of `matrix` with its height, missing values
+    /// stay missing.
+    pub fn apply(&self, matrix: &[Vec<Option<u32>>]) -> Vec<Vec<Option<f64>>> {
+        matrix
+            .iter()
+            .map(|row| {
+                row.iter()
+                    .map(|value| value.map(|value| self.height(value)))
+                    .collect()
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mapping(scale: HeightScale, values: &[u32]) -> HeightMapping {
+        let options = HeightOptions::new(scale, 1.0, 11.0).unwrap();
+        HeightMapping::fit(options, &[values.iter().copied().map(Some).collect()])
+    }
+
+    #[test]
+    fn test_linear() {
+        let mapping = mapping(HeightScale::Linear, &[0, 5, 10]);
+        assert_eq!(mapping.height(0), 1.0);
+        assert_eq!(mapping.height(5), 6.0);
+        assert_eq!(mapping.height(10), 11.0);
+    }
+
+    #[test]
+    fn test_log1p() {
+        let mapping = mapping(HeightScale::Log1p, &[0, 1, 99]);
+        assert_eq!(mapping.height(0), 1.0);
+        assert_eq!(mapping.height(1), 2.51);
+        assert_eq!(mapping.height(99), 11.0);
+    }
+
+    #[test]
+    fn test_sqrt() {
+        let mapping = mapping(HeightScale::Sqrt, &[1, 4, 16]);
+        assert_eq!(mapping.height(4), 6.0);
+        assert_eq!(mapping.height(16), 11.0);
+    }
+
+    #[test]
+    fn test_clamp() {
+        let clamped = mapping(HeightScale::Clamp(10), &[1, 5, 100]);
+        assert_eq!(clamped.height(5), 6.0);
+        assert_eq!(clamped.height(10), 11.0);
+        assert_eq!(clamped.height(100), 11.0);
+
+        // Caps above the highest value behave as linear
+        let unclamped = mapping(HeightScale::Clamp(1000), &[5, 10]);
+        assert_eq!(unclamped.height(5), 6.0);
+
+        // A cap of 0 behaves as a cap of 1 instead of dividing by 0
+        let zero = mapping(HeightScale::Clamp(0), &[0, 1, 5]);
+        assert_eq!(zero.height(0), 1.0);
+        assert_eq!(zero.height(5), 11.0);
+    }
+
+    #[test]
+    fn test_percentile() {
+        let mapping = mapping(HeightScale::Percentile, &[0, 1, 2, 3, 1000]);
+        assert_eq!(mapping.height(0), 1.0);
+        assert_eq!(mapping.height(1), 3.5);
+        assert_eq!(mapping.height(3), 8.5);
+        assert_eq!(mapping.height(1000), 11.0);
+    }
+
+    #[test]
+    fn test_no_contributions() {
+        let mapping = mapping(HeightScale::Linear, &[0, 0]);
+        assert_eq!(mapping.height(0), 1.0);
+    }
+
+    #[test]
+    fn test_apply_keeps_missing_values() {
+        let mapping = mapping(HeightScale::Linear, &[10]);
+        assert_eq!(
+            mapping.apply(&[vec![None, Some(0), Some(10)]]),
+            vec![vec![None, Some(1.0), Some(11.0)]]
+        );
+    }
+
+    #[test]
+    fn test_invalid_heights() {
+        let scale = HeightScale::Linear;
+        assert_eq!(
+            HeightOptions::new(scale, -1.0, 10.0),
+            Err(HeightError::Invalid)
+        );
+        assert_eq!(
+            HeightOptions::new(scale, f64::NAN, 10.0),
+            Err(HeightError::Invalid)
+        );
+        assert_eq!(
+            HeightOptions::new(scale, 2.0, f64::INFINITY),
+            Err(HeightError::Invalid)
+        );
+        assert_eq!(
+            HeightOptions::new(scale, 10.0, 2.0),
+            Err(HeightError::Inverted {
+                min: 10.0,
+                max: 2.0
+            })
+        );
+        let flat = HeightOptions::new(scale, 5.0, 5.0).unwrap();
+        assert_eq!((flat.min_height(), flat.max_height()), (5.0, 5.0));
+    }
+}
@@ -2,6 +2,8 @@
 //! ([OpenSCAD](https://www.openscad.org/) description programs) representing profile data
 //! from GitHub.
 
+/// Save and load activity data as JSON or CSV files.
+pub mod archive;
 /// Librariy with tools to generate 3D models
 /// ([OpenSCAD](https://www.openscad.org/) description programs) representing data
 /// from GitHub user.
@@ -9,4 +11,11 @@ pub mod generators;
 /// Module to download daily activity from GitHub user
 /// profles in a given date range.
 pub mod github;
+/// Map contributions to the heights of the trophy pillars.
+pub mod heights;
+/// Triangle meshes of trophies, written to 3D printing formats without
+/// going through OpenSCAD.
+pub mod mesh;
 pub mod openscad;
+/// Statistics, like totals and streaks, of activity data.
+pub mod stats;
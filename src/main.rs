@@ -1,7 +1,617 @@
-use gh_trophy::generators::generate_openscad;
-use gh_trophy::openscad::resources::trophy_without_data;
+use chrono::{Datelike, NaiveDate};
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use gh_trophy::archive::{ActivityFormat, load_activity, save_activity};
+use gh_trophy::generators::{
+    ComparisonLayout, MatrixValues, Palette, RenderOptions, Team, get_team_activity,
+    openscad_parts, render_comparison_openscad, render_layers_openscad, render_mesh,
+    render_openscad, render_parts, render_team_openscad,
+};
+use gh_trophy::github::activity::{
+    Activity, ActivityFilters, ActivityOptions, MergeMode, WeekStart, get_activity,
+};
+use gh_trophy::github::cache::ActivityCache;
+use gh_trophy::github::client::{GITHUB_API_URL, GitHubClient, GitHubEndpoint, RetryPolicy};
+use gh_trophy::github::organization::get_organization_activity;
+use gh_trophy::heights::{HeightOptions, HeightScale};
+use gh_trophy::mesh::gltf::to_glb;
+use gh_trophy::mesh::obj::{to_mtl, to_obj};
+use gh_trophy::mesh::stl::{to_ascii_stl, to_binary_stl};
+use gh_trophy::mesh::threemf::to_3mf;
+use gh_trophy::mesh::{Part, TrophyShape};
+use gh_trophy::openscad::resources::{
+    comparison_without_data, layers_without_data, trophy_without_data, write_bundle,
+};
+use gh_trophy::openscad::runner::{ExportFormat, OpenScad};
+use gh_trophy::stats::ActivityStats;
+use std::io::Write;
+use std::path::{Path, PathBuf};
 
-use tokio;
+/// Generate an OpenSCAD trophy from the activity of a GitHub user.
+#[derive(Parser, Debug)]
+#[command(version, about, args_conflicts_with_subcommands = true)]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+    // Arguments of the default `generate` command. They are not an optional
+    // flattened `GenerateArgs`, nor `FetchArgs`, as clap does not detect
+    // the presence of optional structs with flattened fields.
+    /// GitHub user profile handle
+    user_handle: Option<String>,
+    #[command(flatten)]
+    query: QueryArgs,
+    #[command(flatten)]
+    render: RenderArgs,
+    #[command(flatten)]
+    output: OutputArgs,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Download the activity of a user and print its OpenSCAD trophy (default)
+    Generate(GenerateArgs),
+    /// Download the activity of a user and save it to a file
+    Fetch {
+        #[command(flatten)]
+        fetch: FetchArgs,
+        /// File the activity is written to
+        #[arg(short, long)]
+        output: PathBuf,
+        /// Format of the file, guessed from its extension by default
+        #[arg(long, value_enum)]
+        format: Option<FileFormat>,
+    },
+    /// Print the OpenSCAD trophy of activity saved with `fetch`
+    Render {
+        /// File the activity is read from
+        input: PathBuf,
+        /// Handle printed on the trophy
+        #[arg(long)]
+        handle: String,
+        /// Format of the file, guessed from its extension by default
+        #[arg(long, value_enum)]
+        format: Option<FileFormat>,
+        #[command(flatten)]
+        render: RenderArgs,
+        #[command(flatten)]
+        output: OutputArgs,
+    },
+    /// Download the activity of a user and print its statistics
+    Stats {
+        #[command(flatten)]
+        fetch: FetchArgs,
+        /// Format of the statistics
+        #[arg(long, value_enum, default_value_t = StatsFormat::Text)]
+        format: StatsFormat,
+    },
+    /// Download the activity of several users and print the OpenSCAD
+    /// trophy of the team
+    Team {
+        /// GitHub user profile handles of the team members
+        #[arg(required = true)]
+        members: Vec<String>,
+        /// Team name printed on the trophy
+        #[arg(long)]
+        name: String,
+        #[command(flatten)]
+        merge: MergeArgs,
+        #[command(flatten)]
+        query: QueryArgs,
+        #[command(flatten)]
+        render: RenderArgs,
+        #[command(flatten)]
+        output: OutputArgs,
+    },
+    /// Download the contributions of the members of an organization to
+    /// it and print the OpenSCAD trophy of the whole organization
+    Org {
+        /// GitHub organization login
+        login: String,
+        /// Name printed on the trophy, the organization login by default
+        #[arg(long)]
+        name: Option<String>,
+        #[command(flatten)]
+        merge: MergeArgs,
+        #[command(flatten)]
+        query: QueryArgs,
+        #[command(flatten)]
+        render: RenderArgs,
+        #[command(flatten)]
+        output: OutputArgs,
+    },
+    /// Download the activity of two users, or of a user over the last two
+    /// years, and print the OpenSCAD trophy comparing them
+    Compare {
+        /// GitHub user profile handle of the first activity
+        left: String,
+        /// GitHub user profile handle of the second activity, the activity
+        /// of the first user over the previous year by default
+        right: Option<String>,
+        /// How both calendars are laid out on the trophy
+        #[arg(long, value_enum, default_value_t = LayoutArg::Mirrored)]
+        layout: LayoutArg,
+        #[command(flatten)]
+        query: QueryArgs,
+        #[command(flatten)]
+        render: RenderArgs,
+        #[command(flatten)]
+        output: OutputArgs,
+    },
+    /// Download the activity of a user over several calendar years and
+    /// print the OpenSCAD trophy with a tier per year
+    Layers {
+        #[command(flatten)]
+        fetch: FetchArgs,
+        /// Number of calendar years, ending with the current one
+        #[arg(
+            long,
+            default_value_t = 3,
+            value_parser = clap::value_parser!(u32).range(1..=100)
+        )]
+        years: u32,
+        #[command(flatten)]
+        render: RenderArgs,
+        #[command(flatten)]
+        output: OutputArgs,
+    },
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum LayoutArg {
+    Mirrored,
+    Interleaved,
+}
+
+impl From<LayoutArg> for ComparisonLayout {
+    fn from(layout: LayoutArg) -> Self {
+        match layout {
+            LayoutArg::Mirrored => ComparisonLayout::Mirrored,
+            LayoutArg::Interleaved => ComparisonLayout::Interleaved,
+        }
+    }
+}
+
+#[derive(Args, Debug)]
+struct MergeArgs {
+    /// How the contributions of the members made on the same day
+    /// are combined
+    #[arg(long, value_enum, default_value_t = MergeArg::Sum)]
+    merge: MergeArg,
+    /// Maximum number of members whose activity is downloaded at once
+    #[arg(long, default_value_t = 4)]
+    parallelism: usize,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum MergeArg {
+    Sum,
+    Max,
+    ActiveMembers,
+}
+
+impl From<MergeArg> for MergeMode {
+    fn from(merge: MergeArg) -> Self {
+        match merge {
+            MergeArg::Sum => MergeMode::Sum,
+            MergeArg::Max => MergeMode::Max,
+            MergeArg::ActiveMembers => MergeMode::ActiveMembers,
+        }
+    }
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum StatsFormat {
+    Text,
+    Json,
+}
+
+#[derive(Args, Debug)]
+struct GenerateArgs {
+    #[command(flatten)]
+    fetch: FetchArgs,
+    #[command(flatten)]
+    render: RenderArgs,
+    #[command(flatten)]
+    output: OutputArgs,
+}
+
+#[derive(Args, Debug)]
+struct OutputArgs {
+    /// Format of the trophy. Mesh formats, every format but `scad` and
+    /// `png`, are built without OpenSCAD and only include the base and
+    /// the pillars unless `--openscad` is given. Comparisons and layered
+    /// trophies are always exported with OpenSCAD, and not as OBJ nor glTF
+    #[arg(long, value_enum, default_value_t = OutputFormat::Scad)]
+    output_format: OutputFormat,
+    /// Export STL and 3MF files with the OpenSCAD executable given as
+    /// `--openscad=PATH`, `openscad` if omitted, including the logo and
+    /// the labels, as parts of their own in 3MF files
+    #[arg(
+        long,
+        env = "OPENSCAD",
+        value_name = "PATH",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "openscad"
+    )]
+    openscad: Option<PathBuf>,
+    /// Colour the pillars of 3MF, OBJ and glTF files by contribution
+    /// level, with the palette of the GitHub contribution graph
+    #[arg(long)]
+    level_colors: bool,
+    /// File the trophy is written to, printed by default
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum OutputFormat {
+    Scad,
+    /// Directory, given with `--output`, with the OpenSCAD template, the
+    /// data file it includes and the logo, rendering without this repository
+    ScadBundle,
+    Stl,
+    StlAscii,
+    /// 3MF with the base and pillars as separate coloured parts, the logo
+    /// and the labels being coloured parts too with `--openscad`
+    #[value(name = "3mf")]
+    ThreeMf,
+    /// Wavefront OBJ with coloured vertices, and an MTL file with the
+    /// colours next to the output file
+    Obj,
+    /// Binary glTF 2.0 with coloured vertices
+    Glb,
+    /// Preview image rendered by OpenSCAD
+    Png,
+}
+
+impl OutputArgs {
+    /// Write the trophy of `activity`, labelled with `label`, in the
+    /// requested format. `scad` builds its OpenSCAD program with the
+    /// given static code.
+    fn write(
+        &self,
+        activity: &Activity,
+        label: &str,
+        render_options: &RenderOptions,
+        scad: impl FnOnce(Option<String>) -> String,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let mesh = || render_mesh(activity, &TrophyShape::default(), render_options);
+        let parts = || {
+            render_parts(
+                activity,
+                &TrophyShape::default(),
+                render_options,
+                &Palette::default(),
+                self.level_colors,
+            )
+        };
+        let bytes = match self.output_format {
+            OutputFormat::Stl if self.openscad.is_none() => to_binary_stl(&mesh()),
+            OutputFormat::StlAscii if self.openscad.is_none() => {
+                to_ascii_stl(&mesh(), label).into_bytes()
+            }
+            OutputFormat::ThreeMf if self.openscad.is_none() => to_3mf(&parts())?,
+            OutputFormat::ThreeMf => {
+                let scad_parts = openscad_parts(
+                    activity,
+                    render_options,
+                    &Palette::default(),
+                    self.level_colors,
+                );
+                let variables: Vec<_> = scad_parts
+                    .iter()
+                    .map(|part| part.variables.clone())
+                    .collect();
+                let openscad = self.openscad.clone().map(OpenScad::new);
+                let meshes = openscad.unwrap_or_default().export_meshes(
+                    "trophy.scad",
+                    &trophy_without_data(),
+                    &scad(None),
+                    &variables,
+                )?;
+                let parts: Vec<Part> = scad_parts
+                    .into_iter()
+                    .zip(meshes)
+                    .map(|(part, mesh)| Part {
+                        name: part.name,
+                        color: part.color,
+                        mesh,
+                    })
+                    .collect();
+                to_3mf(&parts)?
+            }
+            OutputFormat::Obj => {
+                let parts = parts();
+                // Materials are written next to the OBJ file, if any
+                let mtl_path = self.output.as_ref().map(|path| path.with_extension("mtl"));
+                if let Some(mtl_path) = &mtl_path {
+                    std::fs::write(mtl_path, to_mtl(&parts))?;
+                }
+                let mtl_file = mtl_path
+                    .as_ref()
+                    .and_then(|path| path.file_name())
+                    .map(|name| name.to_string_lossy());
+                to_obj(&parts, mtl_file.as_deref()).into_bytes()
+            }
+            OutputFormat::Glb => to_glb(&parts()),
+            // Programs, and meshes exported by OpenSCAD
+            _ => return self.write_program("trophy.scad", trophy_without_data(), scad),
+        };
+        self.write_bytes(&bytes)
+    }
+
+    /// Write the trophy generated with the OpenSCAD `template`, e.g: the
+    /// content of `comparison.scad`, in the requested format. Meshes are
+    /// always exported with OpenSCAD, OBJ and glTF files are not supported.
+    fn write_program(
+        &self,
+        template_name: &str,
+        template: String,
+        scad: impl FnOnce(Option<String>) -> String,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let format = match self.output_format {
+            OutputFormat::Scad => {
+                return self.write_bytes(format!("{}\n", scad(Some(template))).as_bytes());
+            }
+            OutputFormat::ScadBundle => {
+                let dir = self
+                    .output
+                    .as_ref()
+                    .ok_or("The scad-bundle format needs an output directory")?;
+                write_bundle(dir, template_name, &template, &scad(None))?;
+                return Ok(());
+            }
+            OutputFormat::Stl => ExportFormat::BinaryStl,
+            OutputFormat::StlAscii => ExportFormat::AsciiStl,
+            OutputFormat::ThreeMf => ExportFormat::ThreeMf,
+            OutputFormat::Png => ExportFormat::Png,
+            OutputFormat::Obj | OutputFormat::Glb => {
+                let error_msg = "Only trophies of a single activity can be written as OBJ or glTF";
+                return Err(
+                    std::io::Error::new(std::io::ErrorKind::InvalidInput, error_msg).into(),
+                );
+            }
+        };
+        let openscad = self.openscad.clone().map(OpenScad::new);
+        let bytes =
+            openscad
+                .unwrap_or_default()
+                .export(template_name, &template, &scad(None), format)?;
+        self.write_bytes(&bytes)
+    }
+
+    /// Write `bytes` to the output file, or print them.
+    fn write_bytes(&self, bytes: &[u8]) -> Result<(), Box<dyn std::error::Error>> {
+        match &self.output {
+            Some(path) => std::fs::write(path, bytes)?,
+            None => std::io::stdout().write_all(bytes)?,
+        }
+        Ok(())
+    }
+}
+
+#[derive(Args, Debug)]
+struct RenderArgs {
+    /// First day of the weeks on the trophy, `sunday` matches the
+    /// contribution graph of GitHub profiles
+    #[arg(long, value_enum, default_value_t = WeekStartArg::Monday)]
+    week_start: WeekStartArg,
+    /// Emit the contribution level of each day, from 0 to 4, instead of
+    /// its number of contributions
+    #[arg(long)]
+    levels: bool,
+    /// Function mapping contributions to pillar heights
+    #[arg(long, value_enum, default_value_t = HeightScaleArg::Log1p)]
+    height_scale: HeightScaleArg,
+    /// Contributions getting the maximum height with `--height-scale clamp`
+    #[arg(
+        long,
+        value_name = "CONTRIBUTIONS",
+        default_value_t = 20,
+        value_parser = clap::value_parser!(u32).range(1..)
+    )]
+    clamp_at: u32,
+    /// Height, in millimetres, of the pillars of days without contributions
+    #[arg(long, value_name = "MM", default_value_t = HeightOptions::default().min_height())]
+    min_height: f64,
+    /// Height, in millimetres, of the pillars of the busiest days
+    #[arg(long, value_name = "MM", default_value_t = HeightOptions::default().max_height())]
+    max_height: f64,
+    /// Also emit the activity statistics, e.g: `totalContributions` or
+    /// `statsTxt`, as OpenSCAD variables
+    #[arg(long)]
+    stats: bool,
+    /// Also emit the totals reported by GitHub, including the number of
+    /// private contributions, e.g: `restrictedContributions` or `privateTxt`
+    #[arg(long)]
+    totals: bool,
+}
+
+impl RenderArgs {
+    /// Options selected by the arguments, failing if the height range is
+    /// not valid.
+    fn options(&self) -> std::io::Result<RenderOptions> {
+        let scale = match self.height_scale {
+            HeightScaleArg::Linear => HeightScale::Linear,
+            HeightScaleArg::Log1p => HeightScale::Log1p,
+            HeightScaleArg::Sqrt => HeightScale::Sqrt,
+            HeightScaleArg::Clamp => HeightScale::Clamp(self.clamp_at),
+            HeightScaleArg::Percentile => HeightScale::Percentile,
+        };
+        let heights =
+            HeightOptions::new(scale, self.min_height, self.max_height).map_err(|error| {
+                std::io::Error::new(std::io::ErrorKind::InvalidInput, error.to_string())
+            })?;
+        Ok(RenderOptions {
+            week_start: match self.week_start {
+                WeekStartArg::Monday => WeekStart::Monday,
+                WeekStartArg::Sunday => WeekStart::Sunday,
+            },
+            values: if self.levels {
+                MatrixValues::Levels
+            } else {
+                MatrixValues::Counts
+            },
+            heights,
+            stats: self.stats,
+            totals: self.totals,
+        })
+    }
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum WeekStartArg {
+    Monday,
+    Sunday,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum HeightScaleArg {
+    Linear,
+    Log1p,
+    Sqrt,
+    Clamp,
+    Percentile,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum FileFormat {
+    Json,
+    Csv,
+}
+
+impl FileFormat {
+    fn or_guess(format: Option<FileFormat>, path: &Path) -> ActivityFormat {
+        match format {
+            Some(FileFormat::Json) => ActivityFormat::Json,
+            Some(FileFormat::Csv) => ActivityFormat::Csv,
+            None => ActivityFormat::from_path(path),
+        }
+    }
+}
+
+#[derive(Args, Debug)]
+struct FetchArgs {
+    /// GitHub user profile handle
+    user_handle: String,
+    #[command(flatten)]
+    query: QueryArgs,
+}
+
+#[derive(Args, Debug)]
+struct QueryArgs {
+    /// Also emit separate matrices per contribution kind (commits,
+    /// pull requests, issues, reviews and repositories). GitHub only lists
+    /// commits to 100 repositories every 100 days, commits to any other
+    /// repository are missing from these matrices
+    #[arg(long)]
+    by_kind: bool,
+    /// Only count contributions to this repository, can be repeated
+    #[arg(long = "include-repo", value_name = "OWNER/NAME")]
+    include_repositories: Vec<String>,
+    /// Ignore contributions to this repository, can be repeated
+    #[arg(long = "exclude-repo", value_name = "OWNER/NAME")]
+    exclude_repositories: Vec<String>,
+    /// Only count contributions to this organization, can be repeated
+    #[arg(long = "include-org", value_name = "LOGIN")]
+    include_organizations: Vec<String>,
+    /// Ignore contributions to this organization, can be repeated
+    #[arg(long = "exclude-org", value_name = "LOGIN")]
+    exclude_organizations: Vec<String>,
+    /// Base URL of the GitHub REST API, e.g: `https://HOST/api/v3` for
+    /// GitHub Enterprise Server. The GraphQL endpoint is derived from it
+    #[arg(long, env = "GITHUB_API_URL", default_value = GITHUB_API_URL)]
+    api_url: String,
+    /// Times a request is retried after transient or rate limiting errors
+    #[arg(long, default_value_t = RetryPolicy::default().max_retries)]
+    max_retries: u32,
+    /// Reuse contributions downloaded by previous runs, only refreshing
+    /// recent days
+    #[arg(long)]
+    cache: bool,
+    /// Directory of the cache, defaults to `$XDG_CACHE_HOME/gh-trophy`
+    #[arg(long, value_name = "DIR")]
+    cache_dir: Option<PathBuf>,
+    /// Hours during which cached recent days are not downloaded again
+    #[arg(long, value_name = "HOURS", default_value_t = 12)]
+    cache_ttl: i64,
+    /// Only read contributions from the cache, failing if some days are missing.
+    /// Totals are missing unless the same range was downloaded before.
+    /// Implies `--cache`
+    #[arg(long)]
+    offline: bool,
+    /// Keep the contribution levels computed by GitHub, used with
+    /// `--levels`, instead of computing them from the contributions
+    #[arg(long)]
+    github_levels: bool,
+}
+
+impl QueryArgs {
+    /// The last year to the date (From today-365 days to today).
+    fn date_range(&self) -> (NaiveDate, NaiveDate) {
+        let end_date = chrono::Utc::now().naive_utc().date();
+        let start_date = end_date - chrono::Duration::days(365);
+        (start_date, end_date)
+    }
+
+    fn options(&self) -> ActivityOptions {
+        ActivityOptions {
+            by_kind: self.by_kind,
+            filters: ActivityFilters {
+                include_repositories: self.include_repositories.clone(),
+                exclude_repositories: self.exclude_repositories.clone(),
+                include_organizations: self.include_organizations.clone(),
+                exclude_organizations: self.exclude_organizations.clone(),
+            },
+            cache: (self.cache || self.offline).then(|| ActivityCache {
+                dir: self
+                    .cache_dir
+                    .clone()
+                    .unwrap_or_else(ActivityCache::default_dir),
+                ttl: chrono::TimeDelta::hours(self.cache_ttl),
+                offline: self.offline,
+            }),
+            github_levels: self.github_levels,
+            ..Default::default()
+        }
+    }
+
+    fn client(&self) -> GitHubClient {
+        let maybe_token = std::env::var("GITHUB_TOKEN").ok();
+        GitHubClient::new(maybe_token)
+            .with_endpoint(GitHubEndpoint::from_api_url(&self.api_url))
+            .with_retry_policy(RetryPolicy {
+                max_retries: self.max_retries,
+                ..Default::default()
+            })
+    }
+}
+
+/// Download the activity of the last year to the date
+/// (From today-365 days to today).
+async fn fetch(args: &FetchArgs) -> Result<Activity, Box<dyn std::error::Error>> {
+    let query = &args.query;
+    let activity = get_activity(
+        &query.client(),
+        &args.user_handle,
+        query.date_range(),
+        &query.options(),
+    )
+    .await?;
+    warn_truncated_commits(&args.user_handle, &activity);
+    Ok(activity)
+}
+
+/// Warn that commits may be missing from the activity of `label`.
+fn warn_truncated_commits(label: &str, activity: &Activity) {
+    for (start, end) in &activity.truncated_commit_windows {
+        eprintln!(
+            "Warning: {} committed to 100 or more repositories from {} to {}, \
+             commits to the repositories GitHub does not list are not counted",
+            label, start, end
+        );
+    }
+}
 
 /// CLI tool to download user activity data from GitHub
 /// and represent it as OpenSCAD source files that can be
@@ -13,30 +623,397 @@ use tokio;
 /// use it an application token to authenticate with GitHub API,
 /// this makes it possible to include private repositories activity
 /// on the response.
+/// GitHub Enterprise Server instances can be queried by setting the
+/// `GITHUB_API_URL` environment variable or the `--api-url` flag.
+/// Downloaded contributions can be cached on disk with `--cache` and
+/// trophies regenerated without network access with `--offline`.
+/// Contributions can be restricted to some repositories or organizations
+/// and, optionally, broken down by kind. See `--help` for all the flags.
+/// Weeks start on Monday unless `--week-start sunday` is given to lay
+/// out the trophy like the GitHub profile contribution graph.
+/// With `--levels` pillars follow the contribution levels, from 0 to 4,
+/// GitHub uses to colour the days instead of the number of contributions.
+/// Pillar heights are computed with `--height-scale`, between
+/// `--min-height` and `--max-height` millimetres.
+/// With `--totals` the number of private contributions GitHub reports
+/// is emitted so that trophies can mention them.
+/// Trophies can also be written as STL, multi-colour 3MF, OBJ or glTF
+/// files, see `--output-format`, without installing OpenSCAD.
+/// With `--openscad`, STL and 3MF files, as well as PNG previews, are
+/// rendered by the OpenSCAD executable instead, logo and labels included,
+/// as they always are for the `compare` and `layers` subcommands.
+/// Printed programs expect the files of this repository next to them,
+/// `--output-format scad-bundle` writes a directory with everything needed.
+/// The `fetch` and `render` subcommands split downloading the activity to
+/// a JSON or CSV file and generating the trophy from that file.
+/// The `stats` subcommand prints totals, streaks and the busiest days.
+/// The `team` subcommand builds a single trophy from the activity of
+/// several users and the `org` subcommand from the activity of every
+/// member of an organization.
+/// The `compare` subcommand builds a trophy with the calendars of two
+/// users, or of a user over the last two years, side by side.
+/// The `layers` subcommand stacks the activity of several calendar years
+/// as the tiers of a layered trophy.
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let maybe_user_handle = std::env::args().nth(1);
+    let cli = Cli::parse();
 
-    let end_date = chrono::Utc::now().naive_utc().date();
-    let start_date = end_date - chrono::Duration::days(365);
+    let command = match (cli.command, cli.user_handle) {
+        (Some(command), _) => command,
+        (None, Some(user_handle)) => Command::Generate(GenerateArgs {
+            fetch: FetchArgs {
+                user_handle,
+                query: cli.query,
+            },
+            render: cli.render,
+            output: cli.output,
+        }),
+        (None, None) => {
+            let error_msg = "Please provide a GitHub user handle as the first argument";
+            eprintln!("{}", error_msg);
+            return Err(std::io::Error::new(std::io::ErrorKind::InvalidInput, error_msg).into());
+        }
+    };
 
-    if let Some(user_handle) = maybe_user_handle {
-        let maybe_token = std::env::var("GITHUB_TOKEN").ok();
+    match command {
+        Command::Generate(GenerateArgs {
+            fetch: args,
+            render,
+            output,
+        }) => {
+            let render_options = render.options()?;
+            let activity = fetch(&args).await?;
+            output.write(
+                &activity,
+                &args.user_handle,
+                &render_options,
+                |static_code| {
+                    render_openscad(
+                        &activity,
+                        args.user_handle.clone(),
+                        static_code,
+                        &render_options,
+                    )
+                },
+            )?;
+        }
+        Command::Fetch {
+            fetch: args,
+            output,
+            format,
+        } => {
+            let activity = fetch(&args).await?;
+            let format = FileFormat::or_guess(format, &output);
+            std::fs::write(&output, save_activity(&activity, format)?)?;
+        }
+        Command::Render {
+            input,
+            handle,
+            format,
+            render,
+            output,
+        } => {
+            let render_options = render.options()?;
+            let format = FileFormat::or_guess(format, &input);
+            let activity = load_activity(&std::fs::read_to_string(&input)?, format)?;
+            output.write(&activity, &handle, &render_options, |static_code| {
+                render_openscad(&activity, handle.clone(), static_code, &render_options)
+            })?;
+        }
+        Command::Team {
+            members,
+            name,
+            merge,
+            query,
+            render,
+            output,
+        } => {
+            let render_options = render.options()?;
+            let team = Team {
+                merge: merge.merge.into(),
+                parallelism: merge.parallelism,
+                ..Team::new(name, members)
+            };
+            let (start_date, end_date) = query.date_range();
+            let activity = get_team_activity(
+                &query.client(),
+                &team,
+                start_date,
+                end_date,
+                &query.options(),
+            )
+            .await?;
+            warn_truncated_commits(&team.name, &activity);
+            output.write(&activity, &team.name, &render_options, |static_code| {
+                render_team_openscad(&activity, &team, static_code, &render_options)
+            })?;
+        }
+        Command::Org {
+            login,
+            name,
+            merge,
+            query,
+            render,
+            output,
+        } => {
+            let render_options = render.options()?;
+            let organization_activity = get_organization_activity(
+                &query.client(),
+                &login,
+                query.date_range(),
+                &query.options(),
+                merge.merge.into(),
+                merge.parallelism,
+            )
+            .await?;
+            for (member, error) in &organization_activity.failed_members {
+                eprintln!("Skipping the activity of {}: {}", member, error);
+            }
+            warn_truncated_commits(&login, &organization_activity.activity);
+            let team = Team::new(name.unwrap_or(login), organization_activity.members);
+            let activity = &organization_activity.activity;
+            output.write(activity, &team.name, &render_options, |static_code| {
+                render_team_openscad(activity, &team, static_code, &render_options)
+            })?;
+        }
+        Command::Compare {
+            left,
+            right,
+            layout,
+            query,
+            render,
+            output,
+        } => {
+            let render_options = render.options()?;
+            let client = query.client();
+            let options = query.options();
+            let (start_date, end_date) = query.date_range();
+            let left_activity =
+                get_activity(&client, &left, (start_date, end_date), &options).await?;
+            let (left_label, right_label, right_activity) = match right {
+                Some(right) => {
+                    let activity =
+                        get_activity(&client, &right, (start_date, end_date), &options).await?;
+                    (left, right, activity)
+                }
+                None => {
+                    // As many days as the queried range, right before it
+                    let previous_end = start_date - chrono::Duration::days(1);
+                    let previous_start = previous_end - (end_date - start_date);
+                    let activity =
+                        get_activity(&client, &left, (previous_start, previous_end), &options)
+                            .await?;
+                    (
+                        format!("{} ({})", left, end_date.year()),
+                        format!("{} ({})", left, previous_end.year()),
+                        activity,
+                    )
+                }
+            };
+            warn_truncated_commits(&left_label, &left_activity);
+            warn_truncated_commits(&right_label, &right_activity);
+            output.write_program(
+                "comparison.scad",
+                comparison_without_data(),
+                |static_code| {
+                    render_comparison_openscad(
+                        &left_activity,
+                        left_label,
+                        &right_activity,
+                        right_label,
+                        layout.into(),
+                        static_code,
+                        &render_options,
+                    )
+                },
+            )?;
+        }
+        Command::Layers {
+            fetch: args,
+            years,
+            render,
+            output,
+        } => {
+            let render_options = render.options()?;
+            let query = &args.query;
+            let end_date = chrono::Utc::now().naive_utc().date();
+            let first_year = end_date.year() - years as i32 + 1;
+            let start_date = NaiveDate::from_ymd_opt(first_year, 1, 1).ok_or_else(|| {
+                let error_msg = format!("Year {} is out of range", first_year);
+                std::io::Error::new(std::io::ErrorKind::InvalidInput, error_msg)
+            })?;
+            let activity = get_activity(
+                &query.client(),
+                &args.user_handle,
+                (start_date, end_date),
+                &query.options(),
+            )
+            .await?;
+            warn_truncated_commits(&args.user_handle, &activity);
+            output.write_program("layers.scad", layers_without_data(), |static_code| {
+                render_layers_openscad(&activity, args.user_handle, static_code, &render_options)
+            })?;
+        }
+        Command::Stats {
+            fetch: args,
+            format,
+        } => {
+            let stats = ActivityStats::from_activity(&fetch(&args).await?);
+            match format {
+                StatsFormat::Text => print!("{}", stats),
+                StatsFormat::Json => println!("{}", serde_json::to_string_pretty(&stats)?),
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_command() {
+        let cli = Cli::try_parse_from(["gh-trophy", "--levels", "octocat", "--by-kind"]).unwrap();
+        assert!(cli.command.is_none());
+        assert_eq!(cli.user_handle.as_deref(), Some("octocat"));
+        assert!(cli.render.levels);
+        assert!(cli.query.by_kind);
+
+        let cli = Cli::try_parse_from(["gh-trophy", "stats", "octocat"]).unwrap();
+        assert!(matches!(cli.command, Some(Command::Stats { .. })));
+        assert!(cli.user_handle.is_none());
+    }
+
+    #[test]
+    fn test_invalid_heights() {
+        let args = [
+            "gh-trophy",
+            "octocat",
+            "--min-height",
+            "10",
+            "--max-height",
+            "2",
+        ];
+        let cli = Cli::try_parse_from(args).unwrap();
+        let error = cli.render.options().unwrap_err();
+        assert_eq!(error.kind(), std::io::ErrorKind::InvalidInput);
+
+        let cli = Cli::try_parse_from(["gh-trophy", "octocat", "--max-height", "5"]).unwrap();
+        let heights = cli.render.options().unwrap().heights;
+        assert_eq!(heights.max_height(), 5.0);
+    }
+
+    #[test]
+    fn test_openscad_path_requires_equals() {
+        let cli = Cli::try_parse_from(["gh-trophy", "--openscad", "octocat"]).unwrap();
+        assert_eq!(cli.user_handle.as_deref(), Some("octocat"));
+        assert_eq!(cli.output.openscad, Some(PathBuf::from("openscad")));
+
+        let cli =
+            Cli::try_parse_from(["gh-trophy", "--openscad=/opt/openscad", "octocat"]).unwrap();
+        assert_eq!(cli.output.openscad, Some(PathBuf::from("/opt/openscad")));
+    }
+
+    #[test]
+    fn test_compare_output() {
+        let dir = tempfile::tempdir().unwrap();
+        let bundle_dir = dir.path().join("bundle");
+        let output = |format: &str| {
+            let args = [
+                "gh-trophy",
+                "compare",
+                "octocat",
+                "--output-format",
+                format,
+                "-o",
+            ];
+            match Cli::try_parse_from(args.into_iter().chain(bundle_dir.to_str())) {
+                Ok(Cli {
+                    command: Some(Command::Compare { output, .. }),
+                    ..
+                }) => output,
+                cli => panic!("unexpected command line {:?}", cli),
+            }
+        };
+
+        output("scad-bundle")
+            .write_program(
+                "comparison.scad",
+                comparison_without_data(),
+                |static_code| {
+                    assert!(static_code.is_none());
+                    "data".to_string()
+                },
+            )
+            .unwrap();
+        assert!(bundle_dir.join("comparison.scad").is_file());
+        assert_eq!(
+            std::fs::read_to_string(bundle_dir.join("activity-data.scad")).unwrap(),
+            "data"
+        );
+
+        let result =
+            output("obj").write_program("comparison.scad", comparison_without_data(), |_| {
+                String::new()
+            });
+        assert!(result.is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_openscad_3mf_parts() {
+        use gh_trophy::mesh::Mesh;
+        use std::io::Read;
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let stl_path = dir.path().join("triangle.stl");
+        let triangle = Mesh {
+            vertices: vec![[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0]],
+            triangles: vec![[0, 1, 2]],
+        };
+        std::fs::write(&stl_path, to_binary_stl(&triangle)).unwrap();
+        // OpenSCAD exporting the same triangle for every part
+        let openscad_path = dir.path().join("openscad");
+        let script = format!("#!/bin/sh\ncp {} \"$2\"\n", stl_path.display());
+        std::fs::write(&openscad_path, script).unwrap();
+        std::fs::set_permissions(&openscad_path, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+        let output_path = dir.path().join("trophy.3mf");
+        let openscad_arg = format!("--openscad={}", openscad_path.display());
+        let args = ["gh-trophy", "octocat", "--output-format", "3mf"];
+        let cli = Cli::try_parse_from(args.into_iter().chain([
+            openscad_arg.as_str(),
+            "-o",
+            output_path.to_str().unwrap(),
+        ]))
+        .unwrap();
+        let date = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+        let activity = Activity::from_daily_contributions((date, date), vec![(date, 3)]);
+        let render_options = cli.render.options().unwrap();
+        cli.output
+            .write(&activity, "octocat", &render_options, |static_code| {
+                render_openscad(
+                    &activity,
+                    "octocat".to_string(),
+                    static_code,
+                    &render_options,
+                )
+            })
+            .unwrap();
 
-        let result_as_scad_data = generate_openscad(
-            user_handle,
-            start_date,
-            end_date,
-            maybe_token,
-            Some(trophy_without_data()),
-        )
-        .await?;
-
-        println!("{}", result_as_scad_data);
-        return Ok(());
-    } else {
-        let error_msg = "Please provide a GitHub user handle as the first argument";
-        eprintln!("{}", error_msg);
-        return Err(std::io::Error::new(std::io::ErrorKind::InvalidInput, error_msg).into());
+        let file = std::fs::File::open(&output_path).unwrap();
+        let mut model = String::new();
+        zip::ZipArchive::new(file)
+            .unwrap()
+            .by_name("3D/3dmodel.model")
+            .unwrap()
+            .read_to_string(&mut model)
+            .unwrap();
+        for name in ["base", "pillars", "logo", "text"] {
+            assert!(model.contains(format!(" name=\"{}\" type=\"model\"", name).as_str()));
+        }
+        assert_eq!(model.matches("<object ").count(), 4);
     }
 }
@@ -0,0 +1,480 @@
+use std::collections::{BTreeSet, HashMap};
+
+pub mod gltf;
+pub mod obj;
+pub mod stl;
+pub mod threemf;
+
+/// Vertex coordinates, in millimetres.
+pub type Vertex = [f64; 3];
+
+/// Triangle mesh, triangles list the indices of their vertices
+/// counter-clockwise when seen from outside the solid.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Mesh {
+    pub vertices: Vec<Vertex>,
+    pub triangles: Vec<[usize; 3]>,
+}
+
+impl Mesh {
+    /// Unit normal of a triangle, pointing outside the solid.
+    pub fn normal(&self, triangle: [usize; 3]) -> Vertex {
+        let [a, b, c] = triangle.map(|index| self.vertices[index]);
+        let u = [b[0] - a[0], b[1] - a[1], b[2] - a[2]];
+        let v = [c[0] - a[0], c[1] - a[1], c[2] - a[2]];
+        let n = [
+            u[1] * v[2] - u[2] * v[1],
+            u[2] * v[0] - u[0] * v[2],
+            u[0] * v[1] - u[1] * v[0],
+        ];
+        let length = (n[0] * n[0] + n[1] * n[1] + n[2] * n[2]).sqrt();
+        if length == 0.0 {
+            [0.0, 0.0, 0.0]
+        } else {
+            n.map(|coordinate| coordinate / length)
+        }
+    }
+}
+
+/// Colour of a part, in sRGB.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Color(pub [u8; 3]);
+
+impl Color {
+    /// Colour as `#RRGGBB`.
+    pub fn to_hex(self) -> String {
+        let [r, g, b] = self.0;
+        format!("#{:02X}{:02X}{:02X}", r, g, b)
+    }
+}
+
+/// Solid printed in a single colour, e.g: the base of a trophy.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Part {
+    pub name: String,
+    pub color: Color,
+    pub mesh: Mesh,
+}
+
+/// Dimensions, in millimetres, of the trophy built by [`trophy_mesh`].
+/// Defaults mirror the base and pillars of `trophy.scad`, without the
+/// rounded corners, the logo and the texts.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TrophyShape {
+    pub base_length: f64,
+    pub base_width: f64,
+    pub base_height: f64,
+    /// Side of the square pillar of each day.
+    pub pillar_side: f64,
+    /// Distance from the left edge of the base to the first week.
+    pub margin_x: f64,
+    /// Distance from the front edge of the base to the last day of the
+    /// weeks.
+    pub margin_y: f64,
+    /// Gap between the pillars of consecutive weeks, smaller than
+    /// `pillar_side`. Pillars only touching diagonally would otherwise
+    /// share a vertical edge, which slicers reject as non-manifold, while
+    /// they close such a thin gap.
+    pub pillar_gap: f64,
+}
+
+impl Default for TrophyShape {
+    fn default() -> Self {
+        TrophyShape {
+            base_length: 175.0,
+            base_width: 60.0,
+            base_height: 11.0,
+            pillar_side: 3.0,
+            margin_x: 10.0,
+            margin_y: 23.0,
+            pillar_gap: 0.02,
+        }
+    }
+}
+
+/// Coordinates of the lines splitting `[0, length]` in cells, `count`
+/// cells of `side` starting at `margin`, each one inset by half of `gap`
+/// on both sides. The base is stretched if the cells do not fit in it.
+fn grid_lines(length: f64, margin: f64, side: f64, gap: f64, count: usize) -> Vec<f64> {
+    let end = margin + side * count as f64;
+    let mut lines = vec![0.0, margin];
+    for i in 0..count {
+        lines.push(margin + side * i as f64 + gap / 2.0);
+        lines.push(margin + side * (i + 1) as f64 - gap / 2.0);
+    }
+    lines.push(end);
+    lines.push(length.max(end + margin));
+    lines.dedup();
+    lines
+}
+
+/// Cells of the top of a trophy base, days being laid out as in
+/// `trophy.scad`: weeks from left to right and days from the back, the
+/// first day of the week, to the front.
+struct TrophyGrid {
+    xs: Vec<f64>,
+    ys: Vec<f64>,
+    /// Index of the cell of each week, weeks being separated by the
+    /// cells of the gaps between pillars.
+    week_cells: Vec<usize>,
+    /// Index of the first cell of the days.
+    y_offset: usize,
+    days: usize,
+}
+
+impl TrophyGrid {
+    fn new(weeks: usize, days: usize, shape: &TrophyShape) -> Self {
+        let (side, gap) = (shape.pillar_side, shape.pillar_gap);
+        let xs = grid_lines(shape.base_length, shape.margin_x, side, gap, weeks);
+        let ys = grid_lines(shape.base_width, shape.margin_y, side, 0.0, days);
+        TrophyGrid {
+            week_cells: (0..weeks)
+                .map(|week| {
+                    let start = shape.margin_x + side * week as f64 + gap / 2.0;
+                    xs.iter().position(|x| *x == start).unwrap_or(0)
+                })
+                .collect(),
+            y_offset: ys.iter().position(|y| *y == shape.margin_y).unwrap_or(0),
+            xs,
+            ys,
+            days,
+        }
+    }
+
+    /// Week and day of the week of the cell `(i, j)`, which may be out of
+    /// the matrix of the activity.
+    fn day(&self, i: isize, j: isize) -> Option<(usize, usize)> {
+        let week = self
+            .week_cells
+            .binary_search(&usize::try_from(i).ok()?)
+            .ok()?;
+        let reversed_day = usize::try_from(j + 1).ok()?.checked_sub(self.y_offset)?;
+        Some((week, self.days.checked_sub(reversed_day)?))
+    }
+
+    fn contains(&self, i: isize, j: isize) -> bool {
+        i >= 0 && j >= 0 && (i as usize) < self.xs.len() - 1 && (j as usize) < self.ys.len() - 1
+    }
+}
+
+/// Height of the pillar of `day`, as `(week, day of the week)`, if any.
+fn pillar_height(heights: &[Vec<Option<f64>>], day: Option<(usize, usize)>) -> Option<f64> {
+    let (week, day) = day?;
+    heights.get(week)?.get(day).copied().flatten()
+}
+
+/// Build the watertight mesh of a trophy: a base with a pillar on top
+/// for each day of `heights`, matrices of pillar heights as produced by
+/// [`HeightMapping::apply`](crate::heights::HeightMapping::apply) with
+/// weeks as rows. Days without height get no pillar.
+/// The top of the base and of the pillars is split in a grid of cells
+/// sharing their vertices so that the mesh has no holes nor
+/// T-junctions and can be printed without being repaired. Unlike in
+/// `trophy.scad`, pillars of consecutive weeks are separated by
+/// [`TrophyShape::pillar_gap`] to keep the mesh manifold.
+pub fn trophy_mesh(heights: &[Vec<Option<f64>>], shape: &TrophyShape) -> Mesh {
+    let days = heights.iter().map(Vec::len).max().unwrap_or(0);
+    let grid = TrophyGrid::new(heights.len(), days, shape);
+    grid_solid(&grid.xs, &grid.ys, 0.0, |i, j| {
+        if !grid.contains(i, j) {
+            return 0.0;
+        }
+        shape.base_height + pillar_height(heights, grid.day(i, j)).unwrap_or(0.0)
+    })
+}
+
+/// Build the meshes of the base and of the pillars of the trophy of
+/// [`trophy_mesh`] as separate solids, e.g: to print them in different
+/// colours. Pillars are grouped by the key `group_of` their week and day
+/// of the week, each group being a single mesh, sorted by key.
+pub fn trophy_part_meshes<K: Ord>(
+    heights: &[Vec<Option<f64>>],
+    shape: &TrophyShape,
+    group_of: impl Fn(usize, usize) -> K,
+) -> (Mesh, Vec<(K, Mesh)>) {
+    let days = heights.iter().map(Vec::len).max().unwrap_or(0);
+    let grid = TrophyGrid::new(heights.len(), days, shape);
+
+    let (length, width) = (grid.xs[grid.xs.len() - 1], grid.ys[grid.ys.len() - 1]);
+    let base = grid_solid(&[0.0, length], &[0.0, width], 0.0, |i, j| {
+        if i == 0 && j == 0 {
+            shape.base_height
+        } else {
+            0.0
+        }
+    });
+
+    let mut groups: Vec<K> = Vec::new();
+    for (week, days) in heights.iter().enumerate() {
+        for (day, height) in days.iter().enumerate() {
+            let group = group_of(week, day);
+            if height.is_some_and(|height| height > 0.0) && !groups.contains(&group) {
+                groups.push(group);
+            }
+        }
+    }
+    groups.sort();
+
+    let pillars = groups
+        .into_iter()
+        .map(|group| {
+            let mesh = grid_solid(&grid.xs, &grid.ys, shape.base_height, |i, j| {
+                let day = grid.day(i, j).filter(|_| grid.contains(i, j));
+                // Groups are only asked for days in `heights`
+                let height = pillar_height(heights, day)
+                    .filter(|_| day.is_some_and(|(week, day)| group_of(week, day) == group));
+                shape.base_height + height.unwrap_or(0.0)
+            });
+            (group, mesh)
+        })
+        .collect();
+
+    (base, pillars)
+}
+
+/// Build the closed mesh of the solid standing on `ground` with the top
+/// of each cell `(i, j)` of the grid of lines `xs` and `ys` at `top(i, j)`,
+/// cells whose top is at `ground`, as well as those out of the grid, are
+/// empty.
+fn grid_solid(xs: &[f64], ys: &[f64], ground: f64, top: impl Fn(isize, isize) -> f64) -> Mesh {
+    let cells_x = xs.len() - 1;
+    let cells_y = ys.len() - 1;
+    let mut builder = MeshBuilder::default();
+
+    // Heights of the vertical edges on each grid point, walls meeting on a
+    // point are split at each of them so that they share vertices.
+    let breaks = |i: usize, j: usize| -> BTreeSet<u64> {
+        let (i, j) = (i as isize, j as isize);
+        [
+            top(i - 1, j - 1),
+            top(i, j - 1),
+            top(i - 1, j),
+            top(i, j),
+            ground,
+        ]
+        .into_iter()
+        .map(f64::to_bits)
+        .collect()
+    };
+
+    for i in 0..cells_x {
+        for j in 0..cells_y {
+            let z = top(i as isize, j as isize);
+            if z <= ground {
+                continue;
+            }
+            let corners = [(i, j), (i + 1, j), (i + 1, j + 1), (i, j + 1)];
+            let [a, b, c, d] = corners.map(|(x, y)| builder.vertex([xs[x], ys[y], z]));
+            builder.quad(a, b, c, d);
+            let [a, b, c, d] = corners.map(|(x, y)| builder.vertex([xs[x], ys[y], ground]));
+            builder.quad(d, c, b, a);
+        }
+    }
+
+    // Walls between cells, or the cells and the ground, of different
+    // heights, along lines of constant x and then of constant y. Walls
+    // face the lower side, on the right of `p` to `q` seen from above.
+    for i in 0..=cells_x {
+        for j in 0..cells_y {
+            let (left, right) = (top(i as isize - 1, j as isize), top(i as isize, j as isize));
+            let (p, q) = ((i, j), (i, j + 1));
+            if right < left {
+                builder.wall(xs, ys, p, q, right, left, &breaks);
+            } else if left < right {
+                builder.wall(xs, ys, q, p, left, right, &breaks);
+            }
+        }
+    }
+    for j in 0..=cells_y {
+        for i in 0..cells_x {
+            let (front, back) = (top(i as isize, j as isize - 1), top(i as isize, j as isize));
+            let (p, q) = ((i + 1, j), (i, j));
+            if back < front {
+                builder.wall(xs, ys, p, q, back, front, &breaks);
+            } else if front < back {
+                builder.wall(xs, ys, q, p, front, back, &breaks);
+            }
+        }
+    }
+
+    builder.mesh
+}
+
+/// Mesh whose vertices are shared by every triangle using them.
+#[derive(Default)]
+struct MeshBuilder {
+    mesh: Mesh,
+    indices: HashMap<[u64; 3], usize>,
+}
+
+impl MeshBuilder {
+    fn vertex(&mut self, vertex: Vertex) -> usize {
+        let key = vertex.map(f64::to_bits);
+        let vertices = &mut self.mesh.vertices;
+        *self.indices.entry(key).or_insert_with(|| {
+            vertices.push(vertex);
+            vertices.len() - 1
+        })
+    }
+
+    /// Add the counter-clockwise quad `a`, `b`, `c`, `d`.
+    fn quad(&mut self, a: usize, b: usize, c: usize, d: usize) {
+        self.mesh.triangles.push([a, b, c]);
+        self.mesh.triangles.push([a, c, d]);
+    }
+
+    /// Add the vertical wall from grid point `p` to `q` spanning from
+    /// `low` to `high`, facing the right of `p` to `q` seen from above.
+    /// Its vertical edges are split at the `breaks` of their grid point.
+    #[allow(clippy::too_many_arguments)]
+    fn wall(
+        &mut self,
+        xs: &[f64],
+        ys: &[f64],
+        p: (usize, usize),
+        q: (usize, usize),
+        low: f64,
+        high: f64,
+        breaks: &impl Fn(usize, usize) -> BTreeSet<u64>,
+    ) {
+        let mut edge = |point: (usize, usize)| -> Vec<usize> {
+            breaks(point.0, point.1)
+                .into_iter()
+                .map(f64::from_bits)
+                .filter(|z| *z >= low && *z <= high)
+                .map(|z| self.vertex([xs[point.0], ys[point.1], z]))
+                .collect()
+        };
+        let left = edge(p);
+        let right = edge(q);
+
+        // Zip both edges, from the bottom up, into a strip of triangles
+        let z = |index: usize| self.mesh.vertices[index][2];
+        let (mut l, mut r) = (0, 0);
+        while l + 1 < left.len() || r + 1 < right.len() {
+            let advance_left =
+                r + 1 == right.len() || (l + 1 < left.len() && z(left[l + 1]) <= z(right[r + 1]));
+            if advance_left {
+                self.mesh.triangles.push([left[l], right[r], left[l + 1]]);
+                l += 1;
+            } else {
+                self.mesh.triangles.push([left[l], right[r], right[r + 1]]);
+                r += 1;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Every edge of a closed manifold mesh is used by exactly two
+    /// triangles, once in each direction.
+    fn assert_watertight(mesh: &Mesh) {
+        let mut edges: HashMap<(usize, usize), i32> = HashMap::new();
+        for [a, b, c] in mesh.triangles.iter().copied() {
+            for (from, to) in [(a, b), (b, c), (c, a)] {
+                assert_ne!(from, to, "degenerate triangle");
+                *edges.entry((from, to)).or_default() += 1;
+            }
+        }
+        for ((from, to), count) in edges.iter() {
+            let edge = (mesh.vertices[*from], mesh.vertices[*to]);
+            assert_eq!(*count, 1, "edge {:?} used more than once", edge);
+            assert_eq!(
+                edges.get(&(*to, *from)),
+                Some(&1),
+                "edge {:?} not shared",
+                edge
+            );
+        }
+    }
+
+    /// Signed volume enclosed by the mesh, positive if triangles face out.
+    fn volume(mesh: &Mesh) -> f64 {
+        mesh.triangles
+            .iter()
+            .map(|triangle| {
+                let [a, b, c] = triangle.map(|index| mesh.vertices[index]);
+                (a[0] * (b[1] * c[2] - b[2] * c[1]) - a[1] * (b[0] * c[2] - b[2] * c[0])
+                    + a[2] * (b[0] * c[1] - b[1] * c[0]))
+                    / 6.0
+            })
+            .sum()
+    }
+
+    fn shape() -> TrophyShape {
+        TrophyShape {
+            base_length: 10.0,
+            base_width: 8.0,
+            base_height: 1.0,
+            pillar_side: 2.0,
+            margin_x: 1.0,
+            margin_y: 2.0,
+            pillar_gap: 0.5,
+        }
+    }
+
+    #[test]
+    fn test_trophy_mesh_base_only() {
+        let mesh = trophy_mesh(&[], &shape());
+        assert_watertight(&mesh);
+        assert!((volume(&mesh) - 80.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_trophy_mesh_pillars() {
+        let heights = vec![
+            vec![Some(3.0), Some(1.0), None],
+            vec![Some(0.0), Some(3.0), Some(2.0)],
+        ];
+        // The pillars 3 mm high only touch diagonally
+        let mesh = trophy_mesh(&heights, &shape());
+        assert_watertight(&mesh);
+        // Base stretched to fit the pillars, 2 + 3 * 2 + 2 mm wide
+        let pillars = 1.5 * 2.0 * (3.0 + 1.0 + 3.0 + 2.0);
+        assert!((volume(&mesh) - (10.0 * 10.0 + pillars)).abs() < 1e-9);
+
+        // First day of the first week at the back of the base
+        let pillar_top = [1.25, 6.0, 4.0];
+        assert!(mesh.vertices.contains(&pillar_top));
+    }
+
+    #[test]
+    fn test_trophy_part_meshes() {
+        let heights = vec![
+            vec![Some(3.0), Some(1.0), None],
+            vec![Some(0.0), Some(3.0), Some(2.0)],
+        ];
+        // Pillars grouped by their height
+        let (base, pillars) = trophy_part_meshes(&heights, &shape(), |week, day| {
+            heights[week][day].unwrap_or(0.0) as u32
+        });
+        assert_watertight(&base);
+        assert!((volume(&base) - 100.0).abs() < 1e-9);
+
+        let groups: Vec<u32> = pillars.iter().map(|(group, _)| *group).collect();
+        assert_eq!(groups, vec![1, 2, 3]);
+        for (group, mesh) in pillars.iter() {
+            assert_watertight(mesh);
+            let count = if *group == 3 { 2.0 } else { 1.0 };
+            assert!((volume(mesh) - 3.0 * count * *group as f64).abs() < 1e-9);
+            assert!(mesh.vertices.iter().all(|vertex| vertex[2] >= 1.0));
+        }
+    }
+
+    #[test]
+    fn test_color_to_hex() {
+        assert_eq!(Color([0x40, 0xc4, 0x63]).to_hex(), "#40C463");
+    }
+
+    #[test]
+    fn test_normal() {
+        let mesh = Mesh {
+            vertices: vec![[0.0, 0.0, 0.0], [2.0, 0.0, 0.0], [0.0, 2.0, 0.0]],
+            triangles: vec![[0, 1, 2]],
+        };
+        assert_eq!(mesh.normal([0, 1, 2]), [0.0, 0.0, 1.0]);
+    }
+}
@@ -0,0 +1,188 @@
+use serde_json::json;
+
+use super::{Color, Part};
+
+const GLB_MAGIC: &[u8; 4] = b"glTF";
+const GLB_VERSION: u32 = 2;
+const CHUNK_JSON: &[u8; 4] = b"JSON";
+const CHUNK_BIN: &[u8; 4] = b"BIN\0";
+
+/// glTF `componentType` of 32 bits floats and unsigned integers.
+const FLOAT: u32 = 5126;
+const UNSIGNED_INT: u32 = 5125;
+/// glTF buffer view targets of vertex attributes and indices.
+const ARRAY_BUFFER: u32 = 34962;
+const ELEMENT_ARRAY_BUFFER: u32 = 34963;
+
+/// glTF vertex colours are linear while part colours are sRGB.
+fn linear_components(color: Color) -> [f32; 3] {
+    color.0.map(|component| {
+        let c = component as f32 / 255.0;
+        if c <= 0.04045 {
+            c / 12.92
+        } else {
+            ((c + 0.055) / 1.055).powf(2.4)
+        }
+    })
+}
+
+/// Pad `bytes` to a multiple of four bytes, as required by GLB chunks.
+fn pad(bytes: &mut Vec<u8>, padding: u8) {
+    while !bytes.len().is_multiple_of(4) {
+        bytes.push(padding);
+    }
+}
+
+/// Represent `parts` as a binary glTF 2.0 file with a single mesh whose
+/// vertices are coloured as their part. Coordinates are converted from
+/// millimetres with Z up to metres with Y up, as glTF expects.
+/// Normals are not included so that viewers compute flat normals.
+pub fn to_glb(parts: &[Part]) -> Vec<u8> {
+    let mut positions: Vec<[f32; 3]> = Vec::new();
+    let mut colors: Vec<[f32; 3]> = Vec::new();
+    let mut indices: Vec<u32> = Vec::new();
+    for part in parts.iter() {
+        let first_vertex = positions.len() as u32;
+        let color = linear_components(part.color);
+        for [x, y, z] in part.mesh.vertices.iter() {
+            positions.push([*x as f32, *z as f32, -*y as f32].map(|mm| mm / 1000.0));
+            colors.push(color);
+        }
+        indices.extend(
+            part.mesh
+                .triangles
+                .iter()
+                .flatten()
+                .map(|index| *index as u32 + first_vertex),
+        );
+    }
+
+    let mut binary: Vec<u8> = Vec::new();
+    for value in positions.iter().chain(colors.iter()).flatten() {
+        binary.extend_from_slice(&value.to_le_bytes());
+    }
+    for index in indices.iter() {
+        binary.extend_from_slice(&index.to_le_bytes());
+    }
+    let vertices_length = 12 * positions.len();
+    let indices_length = 4 * indices.len();
+
+    let bound = |pick: fn(f32, f32) -> f32, start: f32| {
+        (0..3)
+            .map(|axis| {
+                positions
+                    .iter()
+                    .map(|position| position[axis])
+                    .fold(start, pick)
+            })
+            .collect::<Vec<f32>>()
+    };
+    let (min, max) = if positions.is_empty() {
+        (vec![0.0; 3], vec![0.0; 3])
+    } else {
+        (bound(f32::min, f32::MAX), bound(f32::max, f32::MIN))
+    };
+
+    let document = json!({
+        "asset": {
+            "version": "2.0",
+            "generator": "https://github.com/pfcoperez/gh-trophy"
+        },
+        "scene": 0,
+        "scenes": [{"nodes": [0]}],
+        "nodes": [{"mesh": 0, "name": "trophy"}],
+        "meshes": [{
+            "name": "trophy",
+            "primitives": [{
+                "attributes": {"POSITION": 0, "COLOR_0": 1},
+                "indices": 2,
+                "material": 0
+            }]
+        }],
+        "materials": [{
+            "name": "trophy",
+            "pbrMetallicRoughness": {"metallicFactor": 0.0, "roughnessFactor": 1.0}
+        }],
+        "buffers": [{"byteLength": binary.len()}],
+        "bufferViews": [
+            {"buffer": 0, "byteOffset": 0, "byteLength": vertices_length, "target": ARRAY_BUFFER},
+            {"buffer": 0, "byteOffset": vertices_length, "byteLength": vertices_length, "target": ARRAY_BUFFER},
+            {"buffer": 0, "byteOffset": 2 * vertices_length, "byteLength": indices_length, "target": ELEMENT_ARRAY_BUFFER}
+        ],
+        "accessors": [
+            {"bufferView": 0, "componentType": FLOAT, "count": positions.len(), "type": "VEC3", "min": min, "max": max},
+            {"bufferView": 1, "componentType": FLOAT, "count": colors.len(), "type": "VEC3"},
+            {"bufferView": 2, "componentType": UNSIGNED_INT, "count": indices.len(), "type": "SCALAR"}
+        ]
+    });
+
+    let mut json_chunk = document.to_string().into_bytes();
+    pad(&mut json_chunk, b' ');
+    pad(&mut binary, 0);
+
+    let total_length = 12 + 8 + json_chunk.len() + 8 + binary.len();
+    let mut glb = Vec::with_capacity(total_length);
+    glb.extend_from_slice(GLB_MAGIC);
+    glb.extend_from_slice(&GLB_VERSION.to_le_bytes());
+    glb.extend_from_slice(&(total_length as u32).to_le_bytes());
+    for (chunk_type, chunk) in [(CHUNK_JSON, &json_chunk), (CHUNK_BIN, &binary)] {
+        glb.extend_from_slice(&(chunk.len() as u32).to_le_bytes());
+        glb.extend_from_slice(chunk_type);
+        glb.extend_from_slice(chunk);
+    }
+    glb
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mesh::Mesh;
+
+    fn u32_at(bytes: &[u8], offset: usize) -> u32 {
+        u32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap())
+    }
+
+    #[test]
+    fn test_linear_components() {
+        assert_eq!(linear_components(Color([0, 255, 0])), [0.0, 1.0, 0.0]);
+        let [gray, _, _] = linear_components(Color([128, 128, 128]));
+        assert!((gray - 0.2158).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_to_glb() {
+        let parts = vec![Part {
+            name: "base".to_string(),
+            color: Color([255, 255, 255]),
+            mesh: Mesh {
+                vertices: vec![[0.0, 0.0, 0.0], [1000.0, 0.0, 0.0], [0.0, 2000.0, 500.0]],
+                triangles: vec![[0, 1, 2]],
+            },
+        }];
+        let glb = to_glb(&parts);
+
+        assert_eq!(&glb[0..4], b"glTF");
+        assert_eq!(u32_at(&glb, 4), 2);
+        assert_eq!(u32_at(&glb, 8) as usize, glb.len());
+
+        let json_length = u32_at(&glb, 12) as usize;
+        assert!(json_length.is_multiple_of(4));
+        assert_eq!(&glb[16..20], b"JSON");
+        let document: serde_json::Value =
+            serde_json::from_slice(&glb[20..20 + json_length]).unwrap();
+        let accessors = &document["accessors"];
+        assert_eq!(accessors[0]["count"], 3);
+        assert_eq!(accessors[0]["min"], json!([0.0, 0.0, -2.0]));
+        assert_eq!(accessors[0]["max"], json!([1.0, 0.5, 0.0]));
+        assert_eq!(accessors[2]["count"], 3);
+
+        let bin_offset = 20 + json_length;
+        let bin_length = u32_at(&glb, bin_offset) as usize;
+        assert_eq!(&glb[bin_offset + 4..bin_offset + 8], b"BIN\0");
+        // Positions, colours and indices
+        assert_eq!(bin_length, 36 + 36 + 12);
+        assert_eq!(document["buffers"][0]["byteLength"], bin_length);
+        let bin = &glb[bin_offset + 8..];
+        assert_eq!(u32_at(bin, 72 + 8), 2);
+    }
+}
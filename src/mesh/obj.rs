@@ -0,0 +1,116 @@
+use super::{Color, Part};
+
+/// Name of a part usable as an OBJ object or material name.
+fn obj_name(name: &str) -> String {
+    name.chars()
+        .map(|c| if c.is_whitespace() { '_' } else { c })
+        .collect()
+}
+
+/// Components of `color` from 0 to 1.
+fn color_components(color: Color) -> String {
+    let [r, g, b] = color.0.map(|component| component as f64 / 255.0);
+    format!("{:.4} {:.4} {:.4}", r, g, b)
+}
+
+/// Represent `parts` as a Wavefront OBJ file with an object per part.
+/// Vertices carry the colour of their part, as supported by most
+/// viewers, and objects use the materials of [`to_mtl`] if the name of
+/// its file is given in `mtl_file`.
+pub fn to_obj(parts: &[Part], mtl_file: Option<&str>) -> String {
+    let mut builder = String::new();
+    builder.push_str("# Generated with https://github.com/pfcoperez/gh-trophy\n");
+    if let Some(mtl_file) = mtl_file {
+        builder.push_str(format!("mtllib {}\n", mtl_file).as_str());
+    }
+    // Vertex indices are 1-based and shared by every object of the file
+    let mut first_vertex = 1;
+    for part in parts.iter() {
+        let name = obj_name(&part.name);
+        builder.push_str(format!("o {}\n", name).as_str());
+        if mtl_file.is_some() {
+            builder.push_str(format!("usemtl {}\n", name).as_str());
+        }
+        let color = color_components(part.color);
+        for [x, y, z] in part.mesh.vertices.iter() {
+            builder.push_str(format!("v {} {} {} {}\n", x, y, z, color).as_str());
+        }
+        for triangle in part.mesh.triangles.iter() {
+            let [a, b, c] = triangle.map(|index| index + first_vertex);
+            builder.push_str(format!("f {} {} {}\n", a, b, c).as_str());
+        }
+        first_vertex += part.mesh.vertices.len();
+    }
+    builder
+}
+
+/// Represent the colours of `parts` as a Wavefront MTL file with a
+/// material per part, named after it.
+pub fn to_mtl(parts: &[Part]) -> String {
+    let mut builder = String::new();
+    builder.push_str("# Generated with https://github.com/pfcoperez/gh-trophy\n");
+    for part in parts.iter() {
+        builder.push_str(format!("newmtl {}\n", obj_name(&part.name)).as_str());
+        builder.push_str(format!("Kd {}\n", color_components(part.color)).as_str());
+    }
+    builder
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mesh::Mesh;
+
+    fn parts() -> Vec<Part> {
+        let triangle = Mesh {
+            vertices: vec![[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [0.0, 1.5, 0.0]],
+            triangles: vec![[0, 1, 2]],
+        };
+        vec![
+            Part {
+                name: "base".to_string(),
+                color: Color([0, 0, 0]),
+                mesh: triangle.clone(),
+            },
+            Part {
+                name: "pillars level 4".to_string(),
+                color: Color([255, 0, 51]),
+                mesh: triangle,
+            },
+        ]
+    }
+
+    #[test]
+    fn test_to_obj() {
+        let obj = to_obj(&parts(), Some("trophy.mtl"));
+        let lines: Vec<&str> = obj.lines().collect();
+        assert_eq!(lines[1], "mtllib trophy.mtl");
+        assert_eq!(lines[2], "o base");
+        assert_eq!(lines[3], "usemtl base");
+        assert_eq!(lines[5], "v 1 0 0 0.0000 0.0000 0.0000");
+        assert_eq!(lines[7], "f 1 2 3");
+        assert_eq!(lines[8], "o pillars_level_4");
+        assert_eq!(lines[10], "v 0 0 0 1.0000 0.0000 0.2000");
+        // Indices continue after the vertices of the first object
+        assert_eq!(lines[13], "f 4 5 6");
+    }
+
+    #[test]
+    fn test_to_obj_without_materials() {
+        let obj = to_obj(&parts(), None);
+        assert!(!obj.contains("mtllib"));
+        assert!(!obj.contains("usemtl"));
+    }
+
+    #[test]
+    fn test_to_mtl() {
+        assert_eq!(
+            to_mtl(&parts()),
+            "# Generated with https://github.com/pfcoperez/gh-trophy\n\
+             newmtl base\n\
+             Kd 0.0000 0.0000 0.0000\n\
+             newmtl pillars_level_4\n\
+             Kd 1.0000 0.0000 0.2000\n"
+        );
+    }
+}
@@ -0,0 +1,137 @@
+use std::collections::HashMap;
+
+use super::Mesh;
+
+/// Represent `mesh` as a binary STL file.
+pub fn to_binary_stl(mesh: &Mesh) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(84 + 50 * mesh.triangles.len());
+    let mut header = [0u8; 80];
+    let title = b"Generated with https://github.com/pfcoperez/gh-trophy";
+    header[..title.len()].copy_from_slice(title);
+    bytes.extend_from_slice(&header);
+    bytes.extend_from_slice(&(mesh.triangles.len() as u32).to_le_bytes());
+    for triangle in mesh.triangles.iter().copied() {
+        let points = std::iter::once(mesh.normal(triangle))
+            .chain(triangle.map(|index| mesh.vertices[index]));
+        for point in points {
+            for coordinate in point {
+                bytes.extend_from_slice(&(coordinate as f32).to_le_bytes());
+            }
+        }
+        // Attribute byte count, unused
+        bytes.extend_from_slice(&0u16.to_le_bytes());
+    }
+    bytes
+}
+
+/// Read the mesh of a binary STL file, e.g: exported by OpenSCAD.
+/// Triangles of STL files list their own vertices, identical vertices
+/// are merged so that the mesh stays manifold. Fails if the file is
+/// shorter than the number of triangles it declares.
+pub fn from_binary_stl(bytes: &[u8]) -> Option<Mesh> {
+    let count = u32::from_le_bytes(bytes.get(80..84)?.try_into().ok()?) as usize;
+    let mut mesh = Mesh::default();
+    let mut indices: HashMap<[u32; 3], usize> = HashMap::new();
+    for triangle in 0..count {
+        let offset = 84 + 50 * triangle;
+        let facet = bytes.get(offset..offset + 50)?;
+        // Vertices follow the normal, which is computed again if needed
+        let vertex = |index: usize| -> [f32; 3] {
+            std::array::from_fn(|axis| {
+                let start = 12 * (index + 1) + 4 * axis;
+                f32::from_le_bytes(facet[start..start + 4].try_into().unwrap())
+            })
+        };
+        mesh.triangles.push(std::array::from_fn(|index| {
+            let point = vertex(index);
+            *indices.entry(point.map(f32::to_bits)).or_insert_with(|| {
+                mesh.vertices.push(point.map(f64::from));
+                mesh.vertices.len() - 1
+            })
+        }));
+    }
+    Some(mesh)
+}
+
+/// Represent `mesh` as an ASCII STL file with a solid called `name`.
+pub fn to_ascii_stl(mesh: &Mesh, name: &str) -> String {
+    let name: String = name
+        .chars()
+        .map(|c| if c.is_whitespace() { '_' } else { c })
+        .collect();
+    let mut builder = String::new();
+    builder.push_str(format!("solid {}\n", name).as_str());
+    for triangle in mesh.triangles.iter().copied() {
+        let [nx, ny, nz] = mesh.normal(triangle);
+        builder.push_str(format!("  facet normal {} {} {}\n", nx, ny, nz).as_str());
+        builder.push_str("    outer loop\n");
+        for [x, y, z] in triangle.map(|index| mesh.vertices[index]) {
+            builder.push_str(format!("      vertex {} {} {}\n", x, y, z).as_str());
+        }
+        builder.push_str("    endloop\n");
+        builder.push_str("  endfacet\n");
+    }
+    builder.push_str(format!("endsolid {}\n", name).as_str());
+    builder
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn triangle() -> Mesh {
+        Mesh {
+            vertices: vec![[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [0.0, 1.5, 0.0]],
+            triangles: vec![[0, 1, 2]],
+        }
+    }
+
+    #[test]
+    fn test_binary_stl() {
+        let bytes = to_binary_stl(&triangle());
+        assert_eq!(bytes.len(), 84 + 50);
+        assert!(bytes.starts_with(b"Generated with"));
+        assert_eq!(&bytes[80..84], &1u32.to_le_bytes());
+        let float =
+            |offset: usize| f32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap());
+        // Normal
+        assert_eq!(float(84 + 8), 1.0);
+        // Y of the third vertex
+        assert_eq!(float(84 + 12 + 24 + 4), 1.5);
+    }
+
+    #[test]
+    fn test_from_binary_stl() {
+        // Two triangles sharing an edge
+        let square = Mesh {
+            vertices: vec![
+                [0.0, 0.0, 0.0],
+                [1.0, 0.0, 0.0],
+                [1.0, 1.0, 0.0],
+                [0.0, 1.0, 0.0],
+            ],
+            triangles: vec![[0, 1, 2], [0, 2, 3]],
+        };
+        let bytes = to_binary_stl(&square);
+        assert_eq!(from_binary_stl(&bytes), Some(square));
+        assert_eq!(from_binary_stl(&bytes[..bytes.len() - 1]), None);
+        assert_eq!(from_binary_stl(&bytes[..80]), None);
+    }
+
+    #[test]
+    fn test_ascii_stl() {
+        let text = to_ascii_stl(&triangle(), "my trophy");
+        assert_eq!(
+            text,
+            "solid my_trophy\n\
+             \x20 facet normal 0 0 1\n\
+             \x20   outer loop\n\
+             \x20     vertex 0 0 0\n\
+             \x20     vertex 1 0 0\n\
+             \x20     vertex 0 1.5 0\n\
+             \x20   endloop\n\
+             \x20 endfacet\n\
+             endsolid my_trophy\n"
+        );
+    }
+}
@@ -0,0 +1,169 @@
+use std::io::{Cursor, Write};
+
+use zip::ZipWriter;
+use zip::result::ZipResult;
+use zip::write::SimpleFileOptions;
+
+use super::Part;
+
+const CONTENT_TYPES: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<Types xmlns="http://schemas.openxmlformats.org/package/2006/content-types">
+  <Default Extension="rels" ContentType="application/vnd.openxmlformats-package.relationships+xml"/>
+  <Default Extension="model" ContentType="application/vnd.ms-package.3dmanufacturing-3dmodel+xml"/>
+</Types>
+"#;
+
+const RELATIONSHIPS: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<Relationships xmlns="http://schemas.openxmlformats.org/package/2006/relationships">
+  <Relationship Target="/3D/3dmodel.model" Id="rel0" Type="http://schemas.microsoft.com/3dmanufacturing/2013/01/3dmodel"/>
+</Relationships>
+"#;
+
+/// Escape the characters with a special meaning in XML attributes.
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+/// 3D model XML with an object for each part, coloured with a material
+/// of a single base materials group.
+fn model_xml(parts: &[Part]) -> String {
+    let mut builder = String::new();
+    builder.push_str("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    builder.push_str(
+        "<model unit=\"millimeter\" xml:lang=\"en-US\" \
+         xmlns=\"http://schemas.microsoft.com/3dmanufacturing/core/2015/02\">\n",
+    );
+    builder.push_str(
+        "  <metadata name=\"Application\">https://github.com/pfcoperez/gh-trophy</metadata>\n",
+    );
+    builder.push_str("  <resources>\n");
+    builder.push_str("    <basematerials id=\"1\">\n");
+    for part in parts.iter() {
+        builder.push_str(
+            format!(
+                "      <base name=\"{}\" displaycolor=\"{}FF\"/>\n",
+                escape_xml(&part.name),
+                part.color.to_hex()
+            )
+            .as_str(),
+        );
+    }
+    builder.push_str("    </basematerials>\n");
+    for (index, part) in parts.iter().enumerate() {
+        builder.push_str(
+            format!(
+                "    <object id=\"{}\" name=\"{}\" type=\"model\" pid=\"1\" pindex=\"{}\">\n",
+                index + 2,
+                escape_xml(&part.name),
+                index
+            )
+            .as_str(),
+        );
+        builder.push_str("      <mesh>\n        <vertices>\n");
+        for [x, y, z] in part.mesh.vertices.iter() {
+            builder.push_str(
+                format!("          <vertex x=\"{}\" y=\"{}\" z=\"{}\"/>\n", x, y, z).as_str(),
+            );
+        }
+        builder.push_str("        </vertices>\n        <triangles>\n");
+        for [v1, v2, v3] in part.mesh.triangles.iter() {
+            builder.push_str(
+                format!(
+                    "          <triangle v1=\"{}\" v2=\"{}\" v3=\"{}\"/>\n",
+                    v1, v2, v3
+                )
+                .as_str(),
+            );
+        }
+        builder.push_str("        </triangles>\n      </mesh>\n    </object>\n");
+    }
+    builder.push_str("  </resources>\n  <build>\n");
+    for index in 0..parts.len() {
+        builder.push_str(format!("    <item objectid=\"{}\"/>\n", index + 2).as_str());
+    }
+    builder.push_str("  </build>\n</model>\n");
+    builder
+}
+
+/// Represent `parts` as a 3MF file, each part being a separate object
+/// with its own colour so that multi-material printers print them with
+/// different filaments.
+/// 3MF requires manifold objects, like the meshes built by
+/// [`trophy_part_meshes`](super::trophy_part_meshes).
+pub fn to_3mf(parts: &[Part]) -> ZipResult<Vec<u8>> {
+    let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+    let options = SimpleFileOptions::default();
+    for (name, content) in [
+        ("[Content_Types].xml", CONTENT_TYPES.to_string()),
+        ("_rels/.rels", RELATIONSHIPS.to_string()),
+        ("3D/3dmodel.model", model_xml(parts)),
+    ] {
+        zip.start_file(name, options)?;
+        zip.write_all(content.as_bytes())?;
+    }
+    Ok(zip.finish()?.into_inner())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mesh::{Color, Mesh};
+    use std::io::Read;
+
+    fn parts() -> Vec<Part> {
+        vec![
+            Part {
+                name: "base".to_string(),
+                color: Color([0x24, 0x29, 0x2f]),
+                mesh: Mesh {
+                    vertices: vec![[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [0.0, 1.5, 0.0]],
+                    triangles: vec![[0, 1, 2]],
+                },
+            },
+            Part {
+                name: "pillars <level 4>".to_string(),
+                color: Color([0x21, 0x6e, 0x39]),
+                mesh: Mesh::default(),
+            },
+        ]
+    }
+
+    #[test]
+    fn test_model_xml() {
+        let xml = model_xml(&parts());
+        assert!(xml.contains("<base name=\"base\" displaycolor=\"#24292FFF\"/>"));
+        assert!(
+            xml.contains("<base name=\"pillars &lt;level 4&gt;\" displaycolor=\"#216E39FF\"/>")
+        );
+        assert!(
+            xml.contains("<object id=\"2\" name=\"base\" type=\"model\" pid=\"1\" pindex=\"0\">")
+        );
+        assert!(xml.contains("<vertex x=\"0\" y=\"1.5\" z=\"0\"/>"));
+        assert!(xml.contains("<triangle v1=\"0\" v2=\"1\" v3=\"2\"/>"));
+        assert!(xml.contains("<object id=\"3\" name=\"pillars &lt;level 4&gt;\""));
+        assert!(xml.contains("<item objectid=\"2\"/>\n    <item objectid=\"3\"/>"));
+    }
+
+    #[test]
+    fn test_to_3mf() {
+        let bytes = to_3mf(&parts()).unwrap();
+        let mut archive = zip::ZipArchive::new(Cursor::new(bytes)).unwrap();
+        let mut names: Vec<&str> = archive.file_names().collect();
+        names.sort();
+        assert_eq!(
+            names,
+            vec!["3D/3dmodel.model", "[Content_Types].xml", "_rels/.rels"]
+        );
+        let mut model = String::new();
+        archive
+            .by_name("3D/3dmodel.model")
+            .unwrap()
+            .read_to_string(&mut model)
+            .unwrap();
+        assert_eq!(model, model_xml(&parts()));
+    }
+}
@@ -1,2 +1,3 @@
 pub mod generators;
 pub mod resources;
+pub mod runner;
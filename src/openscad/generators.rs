@@ -1,36 +1,232 @@
-use std::fmt::Debug;
+/// Values that can be written as OpenSCAD literals.
+pub trait ScadValue {
+    fn to_scad(&self) -> String;
+}
+
+macro_rules! scad_value_as_number {
+    ($($t:ty),*) => {
+        $(
+            impl ScadValue for $t {
+                fn to_scad(&self) -> String {
+                    self.to_string()
+                }
+            }
+        )*
+    };
+}
+
+scad_value_as_number!(i32, i64, u32, u64, usize, f32, f64);
+
+impl ScadValue for bool {
+    fn to_scad(&self) -> String {
+        self.to_string()
+    }
+}
+
+/// OpenSCAD strings only need backslashes and double quotes escaped,
+/// any other character is written as is.
+impl ScadValue for &str {
+    fn to_scad(&self) -> String {
+        let mut literal = String::with_capacity(self.len() + 2);
+        literal.push('"');
+        for c in self.chars() {
+            if c == '\\' || c == '"' {
+                literal.push('\\');
+            }
+            literal.push(c);
+        }
+        literal.push('"');
+        literal
+    }
+}
+
+impl ScadValue for String {
+    fn to_scad(&self) -> String {
+        self.as_str().to_scad()
+    }
+}
+
+/// Missing values, e.g: days out of the activity date range, are `undef`.
+impl<T: ScadValue> ScadValue for Option<T> {
+    fn to_scad(&self) -> String {
+        match self {
+            Some(value) => value.to_scad(),
+            None => "undef".to_string(),
+        }
+    }
+}
+
+impl<T: ScadValue> ScadValue for [T] {
+    fn to_scad(&self) -> String {
+        let items: Vec<String> = self.iter().map(ScadValue::to_scad).collect();
+        format!("[{}]", items.join(", "))
+    }
+}
+
+impl<T: ScadValue> ScadValue for Vec<T> {
+    fn to_scad(&self) -> String {
+        self.as_slice().to_scad()
+    }
+}
+
+fn push_matrix<T: ScadValue>(builder: &mut String, name: &str, matrix: &[Vec<T>]) {
+    builder.push_str(format!("{} = [\n", name).as_str());
+    for row in matrix.iter() {
+        builder.push_str(format!("    {},\n", row.to_scad()).as_str());
+    }
+    builder.push_str("];\n");
+}
+
+/// Write `matrices` as an OpenSCAD array of matrices named `name`.
+fn push_matrix_array<T: ScadValue>(builder: &mut String, name: &str, matrices: &[Vec<Vec<T>>]) {
+    builder.push_str(format!("{} = [\n", name).as_str());
+    for matrix in matrices.iter() {
+        builder.push_str("    [\n");
+        for row in matrix.iter() {
+            builder.push_str(format!("        {},\n", row.to_scad()).as_str());
+        }
+        builder.push_str("    ],\n");
+    }
+    builder.push_str("];\n");
+}
+
+const HEADER: &str = "// Generated with https://github.com/pfcoperez/gh-trophy\n//\n";
+
+fn push_variables(builder: &mut String, variables: &[(String, String)]) {
+    for (name, expression) in variables.iter() {
+        builder.push_str(format!("{} = {};\n", name, expression).as_str());
+    }
+}
+
+fn push_static_code(builder: &mut String, maybe_static_code: Option<String>) {
+    maybe_static_code.iter().for_each(|static_code| {
+        builder.push_str(format!("\n\n{}", static_code).as_str());
+    });
+}
+
+/// Matrix of values, e.g: the activity of a user, with the text
+/// labelling it.
+#[derive(Debug, Clone)]
+pub struct Dataset<T> {
+    /// Name of the OpenSCAD variable holding the matrix.
+    pub name: String,
+    /// Name of the OpenSCAD variable holding the label, and the label.
+    pub label: Option<(String, String)>,
+    pub matrix: Vec<Vec<T>>,
+}
 
+impl<T> Dataset<T> {
+    /// Dataset without label.
+    pub fn new(name: String, matrix: Vec<Vec<T>>) -> Self {
+        Dataset {
+            name,
+            label: None,
+            matrix,
+        }
+    }
+
+    /// Dataset labelled in the `<name>Txt` variable.
+    pub fn labelled(name: String, label: String, matrix: Vec<Vec<T>>) -> Self {
+        Dataset {
+            label: Some((format!("{}Txt", name), label)),
+            name,
+            matrix,
+        }
+    }
+}
+
+/// Build an OpenSCAD program defining the `rawActivity` matrix, the
+/// `extra_matrices`, the `ghHandleTxt` and `spanTxt` texts and the
+/// `variables`, each assigned an OpenSCAD expression, followed by the
+/// static code if any.
 pub fn generate_data_source<T>(
     user_handle: String,
     date_str: String,
     activity_data: Vec<Vec<T>>,
+    extra_matrices: Vec<(String, Vec<Vec<T>>)>,
+    variables: Vec<(String, String)>,
+    maybe_static_code: Option<String>,
+) -> String
+where
+    T: ScadValue,
+{
+    let activity = Dataset {
+        name: "rawActivity".to_string(),
+        label: Some(("ghHandleTxt".to_string(), user_handle)),
+        matrix: activity_data,
+    };
+    let datasets = std::iter::once(activity)
+        .chain(
+            extra_matrices
+                .into_iter()
+                .map(|(name, matrix)| Dataset::new(name, matrix)),
+        )
+        .collect();
+    generate_datasets_source(datasets, date_str, variables, maybe_static_code)
+}
+
+/// Build an OpenSCAD program defining the matrix of each dataset, the
+/// labels of the datasets, the `spanTxt` text and the `variables`,
+/// followed by the static code if any.
+pub fn generate_datasets_source<T>(
+    datasets: Vec<Dataset<T>>,
+    date_str: String,
+    variables: Vec<(String, String)>,
     maybe_static_code: Option<String>,
 ) -> String
 where
-    T: ToString,
-    T: Debug,
+    T: ScadValue,
 {
     let mut builder = String::new();
 
-    builder.push_str("// Generated with https://github.com/pfcoperez/gh-trophy\n");
-    builder.push_str("//\n");
-    builder.push_str("rawActivity = [\n");
-    for row in activity_data.iter() {
-        builder.push_str(format!("    {:?},\n", row).as_str());
+    builder.push_str(HEADER);
+    for dataset in datasets.iter() {
+        push_matrix(&mut builder, &dataset.name, &dataset.matrix);
     }
-    builder.push_str("];\n");
 
-    builder.push_str("\n");
-    builder.push_str("\n");
+    builder.push('\n');
+    builder.push('\n');
 
-    builder.push_str(format!("ghHandleTxt = \"{}\";\n", user_handle).as_str());
-    builder.push_str(format!("spanTxt = \"{}\";\n", date_str).as_str());
+    for (name, label) in datasets.iter().filter_map(|dataset| dataset.label.as_ref()) {
+        builder.push_str(format!("{} = {};\n", name, label.to_scad()).as_str());
+    }
+    builder.push_str(format!("spanTxt = {};\n", date_str.to_scad()).as_str());
+    push_variables(&mut builder, &variables);
+    push_static_code(&mut builder, maybe_static_code);
 
-    maybe_static_code.iter().for_each(|static_code| {
-        builder.push_str(format!("\n\n{}", static_code).as_str());
-    });
+    builder
+}
+
+/// Build an OpenSCAD program defining the `layerActivity` array with the
+/// matrix of each layer, e.g: each year of the activity, and their labels
+/// in the `layerTxt` array, followed by the `ghHandleTxt` and `spanTxt`
+/// texts, the `variables` and the static code if any.
+pub fn generate_layers_source<T>(
+    user_handle: String,
+    date_str: String,
+    layers: Vec<(String, Vec<Vec<T>>)>,
+    variables: Vec<(String, String)>,
+    maybe_static_code: Option<String>,
+) -> String
+where
+    T: ScadValue,
+{
+    let (labels, matrices): (Vec<String>, Vec<Vec<Vec<T>>>) = layers.into_iter().unzip();
+    let mut builder = String::new();
+
+    builder.push_str(HEADER);
+    push_matrix_array(&mut builder, "layerActivity", &matrices);
+
+    builder.push('\n');
+    builder.push('\n');
 
-    return builder;
+    builder.push_str(format!("layerTxt = {};\n", labels.to_scad()).as_str());
+    builder.push_str(format!("ghHandleTxt = {};\n", user_handle.to_scad()).as_str());
+    builder.push_str(format!("spanTxt = {};\n", date_str.to_scad()).as_str());
+    push_variables(&mut builder, &variables);
+    push_static_code(&mut builder, maybe_static_code);
+
+    builder
 }
 
 #[cfg(test)]
@@ -40,22 +236,34 @@ mod tests {
     #[test]
     fn test_generate_matrix_source_empty() {
         let data: Vec<Vec<i32>> = vec![];
-        let result = generate_data_source("test".to_string(), "date-str".to_string(), data, None);
+        let result = generate_data_source(
+            "test".to_string(),
+            "date-str".to_string(),
+            data,
+            vec![],
+            vec![],
+            None,
+        );
 
-        assert!(result.contains("// To be used in other OpenScad source file with"));
-        assert!(result.contains("// include <test.scad>"));
-        assert!(result.contains("test = ["));
-        assert!(result.contains("];"));
+        assert!(result.contains("// Generated with https://github.com/pfcoperez/gh-trophy"));
+        assert!(result.contains("rawActivity = [\n];"));
+        assert!(result.contains("ghHandleTxt = \"test\";"));
     }
 
     #[test]
     fn test_generate_matrix_source_single_row() {
         let data = vec![vec![1, 2, 3]];
-        let result =
-            generate_data_source("myMatrix".to_string(), "date-str".to_string(), data, None);
+        let result = generate_data_source(
+            "myMatrix".to_string(),
+            "date-str".to_string(),
+            data,
+            vec![],
+            vec![],
+            None,
+        );
 
-        assert!(result.contains("// include <myMatrix.scad>"));
-        assert!(result.contains("myMatrix = ["));
+        assert!(result.contains("ghHandleTxt = \"myMatrix\";"));
+        assert!(result.contains("rawActivity = ["));
         assert!(result.contains("[1, 2, 3],"));
         assert!(result.contains("];"));
     }
@@ -63,11 +271,17 @@ mod tests {
     #[test]
     fn test_generate_matrix_source_multiple_rows() {
         let data = vec![vec![1, 2, 3], vec![4, 5, 6], vec![7, 8, 9]];
-        let result =
-            generate_data_source("matrix3x3".to_string(), "date-str".to_string(), data, None);
+        let result = generate_data_source(
+            "matrix3x3".to_string(),
+            "date-str".to_string(),
+            data,
+            vec![],
+            vec![],
+            None,
+        );
 
-        assert!(result.contains("// include <matrix3x3.scad>"));
-        assert!(result.contains("matrix3x3 = ["));
+        assert!(result.contains("ghHandleTxt = \"matrix3x3\";"));
+        assert!(result.contains("rawActivity = ["));
         assert!(result.contains("[1, 2, 3],"));
         assert!(result.contains("[4, 5, 6],"));
         assert!(result.contains("[7, 8, 9],"));
@@ -77,7 +291,14 @@ mod tests {
     #[test]
     fn test_generate_matrix_source_with_zeros() {
         let data = vec![vec![0, 0, 0], vec![0, 1, 0]];
-        let result = generate_data_source("sparse".to_string(), "date-str".to_string(), data, None);
+        let result = generate_data_source(
+            "sparse".to_string(),
+            "date-str".to_string(),
+            data,
+            vec![],
+            vec![],
+            None,
+        );
 
         assert!(result.contains("[0, 0, 0],"));
         assert!(result.contains("[0, 1, 0],"));
@@ -86,7 +307,14 @@ mod tests {
     #[test]
     fn test_generate_matrix_source_large_numbers() {
         let data = vec![vec![100, 999, 1234567]];
-        let result = generate_data_source("large".to_string(), "date-str".to_string(), data, None);
+        let result = generate_data_source(
+            "large".to_string(),
+            "date-str".to_string(),
+            data,
+            vec![],
+            vec![],
+            None,
+        );
 
         assert!(result.contains("[100, 999, 1234567],"));
     }
@@ -94,28 +322,69 @@ mod tests {
     #[test]
     fn test_generate_matrix_source_format() {
         let data = vec![vec![1]];
-        let result = generate_data_source("format".to_string(), "date-str".to_string(), data, None);
+        let result = generate_data_source(
+            "format".to_string(),
+            "date-str".to_string(),
+            data,
+            vec![],
+            vec![],
+            None,
+        );
 
         let lines: Vec<&str> = result.lines().collect();
-        assert_eq!(lines.len(), 5);
-        assert_eq!(lines[0], "// To be used in other OpenScad source file with");
-        assert_eq!(lines[1], "// include <format.scad>");
-        assert_eq!(lines[2], "format = [");
+        assert_eq!(lines.len(), 9);
+        assert_eq!(
+            lines[0],
+            "// Generated with https://github.com/pfcoperez/gh-trophy"
+        );
+        assert_eq!(lines[1], "//");
+        assert_eq!(lines[2], "rawActivity = [");
         assert!(lines[3].starts_with("    [1]"));
         assert_eq!(lines[4], "];");
+        assert_eq!(lines[7], "ghHandleTxt = \"format\";");
+        assert_eq!(lines[8], "spanTxt = \"date-str\";");
     }
 
     #[test]
     fn test_generate_matrix_source_different_types() {
         // Test with u32 (used in real code)
         let data: Vec<Vec<u32>> = vec![vec![10, 20], vec![30, 40]];
-        let result =
-            generate_data_source("u32_matrix".to_string(), "date-str".to_string(), data, None);
+        let result = generate_data_source(
+            "u32_matrix".to_string(),
+            "date-str".to_string(),
+            data,
+            vec![],
+            vec![],
+            None,
+        );
 
         assert!(result.contains("[10, 20],"));
         assert!(result.contains("[30, 40],"));
     }
 
+    #[test]
+    fn test_str_to_scad() {
+        assert_eq!("octocat".to_scad(), "\"octocat\"");
+        assert_eq!("José's".to_scad(), "\"José's\"");
+        assert_eq!(r#"a "b" \ c"#.to_scad(), r#""a \"b\" \\ c""#);
+    }
+
+    #[test]
+    fn test_generate_data_source_escapes_texts() {
+        let data: Vec<Vec<i32>> = vec![];
+        let result = generate_data_source(
+            "\"quoted\"".to_string(),
+            "C:\\span".to_string(),
+            data,
+            vec![],
+            vec![],
+            None,
+        );
+
+        assert!(result.contains(r#"ghHandleTxt = "\"quoted\"";"#));
+        assert!(result.contains(r#"spanTxt = "C:\\span";"#));
+    }
+
     #[test]
     fn test_generate_matrix_source_strings() {
         // Test with strings to verify generic type parameter works
@@ -124,6 +393,8 @@ mod tests {
             "string_matrix".to_string(),
             "date-str".to_string(),
             data,
+            vec![],
+            vec![],
             None,
         );
 
@@ -138,11 +409,123 @@ mod tests {
     fn test_generate_matrix_source_irregular_matrix() {
         // Test with rows of different lengths
         let data = vec![vec![1, 2, 3, 4], vec![5, 6], vec![7]];
-        let result =
-            generate_data_source("irregular".to_string(), "date-str".to_string(), data, None);
+        let result = generate_data_source(
+            "irregular".to_string(),
+            "date-str".to_string(),
+            data,
+            vec![],
+            vec![],
+            None,
+        );
 
         assert!(result.contains("[1, 2, 3, 4],"));
         assert!(result.contains("[5, 6],"));
         assert!(result.contains("[7],"));
     }
+
+    #[test]
+    fn test_generate_matrix_source_extra_matrices() {
+        let data = vec![vec![3, 1]];
+        let extra = vec![
+            ("rawCommits".to_string(), vec![vec![2, 1]]),
+            ("rawReviews".to_string(), vec![vec![1, 0]]),
+        ];
+        let result = generate_data_source(
+            "extra".to_string(),
+            "date-str".to_string(),
+            data,
+            extra,
+            vec![],
+            None,
+        );
+
+        assert!(result.contains("rawActivity = [\n    [3, 1],\n];"));
+        assert!(result.contains("rawCommits = [\n    [2, 1],\n];"));
+        assert!(result.contains("rawReviews = [\n    [1, 0],\n];"));
+    }
+
+    #[test]
+    fn test_generate_matrix_source_missing_values() {
+        let data = vec![vec![None, Some(2)], vec![Some(0), None]];
+        let result = generate_data_source(
+            "missing".to_string(),
+            "date-str".to_string(),
+            data,
+            vec![],
+            vec![],
+            None,
+        );
+
+        assert!(result.contains("[undef, 2],"));
+        assert!(result.contains("[0, undef],"));
+    }
+
+    #[test]
+    fn test_generate_matrix_source_variables() {
+        let variables = vec![
+            ("totalContributions".to_string(), 1234.to_scad()),
+            ("statsTxt".to_string(), "1,234 contributions".to_scad()),
+        ];
+        let result = generate_data_source(
+            "vars".to_string(),
+            "date-str".to_string(),
+            vec![vec![1]],
+            vec![],
+            variables,
+            None,
+        );
+
+        assert!(result.contains("spanTxt = \"date-str\";\ntotalContributions = 1234;\n"));
+        assert!(result.contains("statsTxt = \"1,234 contributions\";\n"));
+    }
+
+    #[test]
+    fn test_generate_datasets_source() {
+        let datasets = vec![
+            Dataset::labelled(
+                "leftActivity".to_string(),
+                "alice".to_string(),
+                vec![vec![Some(1), None]],
+            ),
+            Dataset::labelled(
+                "rightActivity".to_string(),
+                "bob".to_string(),
+                vec![vec![None, Some(2)], vec![Some(3), None]],
+            ),
+        ];
+        let variables = vec![("comparisonLayout".to_string(), "mirrored".to_scad())];
+        let result = generate_datasets_source(datasets, "date-str".to_string(), variables, None);
+
+        assert!(
+            result.starts_with("// Generated with https://github.com/pfcoperez/gh-trophy\n//\n")
+        );
+        assert!(result.contains("leftActivity = [\n    [1, undef],\n];"));
+        assert!(result.contains("rightActivity = [\n    [undef, 2],\n    [3, undef],\n];"));
+        assert!(result.contains("leftActivityTxt = \"alice\";\nrightActivityTxt = \"bob\";\n"));
+        assert!(result.contains("spanTxt = \"date-str\";\ncomparisonLayout = \"mirrored\";\n"));
+    }
+
+    #[test]
+    fn test_generate_layers_source() {
+        let layers = vec![
+            ("2023".to_string(), vec![vec![None, Some(1)]]),
+            (
+                "2024".to_string(),
+                vec![vec![Some(2), None], vec![Some(3), None]],
+            ),
+        ];
+        let result = generate_layers_source(
+            "layers".to_string(),
+            "date-str".to_string(),
+            layers,
+            vec![],
+            None,
+        );
+
+        assert!(result.contains(
+            "layerActivity = [\n    [\n        [undef, 1],\n    ],\n    [\n        [2, undef],\n        [3, undef],\n    ],\n];"
+        ));
+        assert!(result.contains("layerTxt = [\"2023\", \"2024\"];\nghHandleTxt = \"layers\";\n"));
+        assert!(result.contains("spanTxt = \"date-str\";"));
+    }
 }
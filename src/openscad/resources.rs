@@ -1,4 +1,5 @@
 use rust_embed::RustEmbed;
+use std::path::{Path, PathBuf};
 
 #[derive(RustEmbed)]
 #[folder = "openscad/"]
@@ -9,5 +10,73 @@ struct StaticFiles;
 
 pub fn trophy_without_data() -> String {
     let raw_bytes = StaticFiles::get("trophy.scad").unwrap().data.to_vec();
-    return String::from_utf8(raw_bytes).unwrap();
+    String::from_utf8(raw_bytes).unwrap()
+}
+
+pub fn comparison_without_data() -> String {
+    let raw_bytes = StaticFiles::get("comparison.scad").unwrap().data.to_vec();
+    String::from_utf8(raw_bytes).unwrap()
+}
+
+pub fn layers_without_data() -> String {
+    let raw_bytes = StaticFiles::get("layers.scad").unwrap().data.to_vec();
+    String::from_utf8(raw_bytes).unwrap()
+}
+
+/// GitHub logo imported by the templates as `images/github.svg`.
+pub fn github_logo() -> Vec<u8> {
+    StaticFiles::get("images/github.svg").unwrap().data.to_vec()
+}
+
+/// Name of the data file included by the templates.
+pub const DATA_FILE: &str = "activity-data.scad";
+/// Path of the logo imported by the templates, relative to them.
+pub const LOGO_FILE: &str = "images/github.svg";
+
+/// Write a program OpenSCAD can render on its own to `dir`: the template
+/// as `template_name`, the program `data` generated without static code
+/// as the data file it includes, and the logo it imports.
+/// Returns the path of the template.
+pub fn write_bundle(
+    dir: &Path,
+    template_name: &str,
+    template: &str,
+    data: &str,
+) -> std::io::Result<PathBuf> {
+    let logo_path = dir.join(LOGO_FILE);
+    if let Some(images_dir) = logo_path.parent() {
+        std::fs::create_dir_all(images_dir)?;
+    }
+    std::fs::write(&logo_path, github_logo())?;
+    std::fs::write(dir.join(DATA_FILE), data)?;
+    let template_path = dir.join(template_name);
+    std::fs::write(&template_path, template)?;
+    Ok(template_path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_write_bundle() {
+        let dir = tempfile::tempdir().unwrap();
+        let bundle_dir = dir.path().join("bundle");
+
+        let template_path =
+            write_bundle(&bundle_dir, "trophy.scad", &trophy_without_data(), "data").unwrap();
+
+        assert_eq!(template_path, bundle_dir.join("trophy.scad"));
+        let template = std::fs::read_to_string(&template_path).unwrap();
+        assert!(template.starts_with(&format!("include <{}>", DATA_FILE)));
+        assert!(template.contains(&format!("import(\"{}\")", LOGO_FILE)));
+        assert_eq!(
+            std::fs::read_to_string(bundle_dir.join(DATA_FILE)).unwrap(),
+            "data"
+        );
+        assert_eq!(
+            std::fs::read(bundle_dir.join(LOGO_FILE)).unwrap(),
+            github_logo()
+        );
+    }
 }
@@ -0,0 +1,266 @@
+use std::path::{Path, PathBuf};
+use std::process::{Command, ExitStatus};
+
+use crate::mesh::Mesh;
+use crate::mesh::stl::from_binary_stl;
+use crate::openscad::resources::write_bundle;
+
+/// Files OpenSCAD can export trophies to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    BinaryStl,
+    AsciiStl,
+    ThreeMf,
+    /// Preview image of the trophy.
+    Png,
+}
+
+impl ExportFormat {
+    fn extension(self) -> &'static str {
+        match self {
+            ExportFormat::BinaryStl | ExportFormat::AsciiStl => "stl",
+            ExportFormat::ThreeMf => "3mf",
+            ExportFormat::Png => "png",
+        }
+    }
+
+    /// Arguments of OpenSCAD selecting the format.
+    fn arguments(self) -> Vec<&'static str> {
+        match self {
+            ExportFormat::BinaryStl => vec!["--export-format", "binstl"],
+            ExportFormat::AsciiStl => vec!["--export-format", "asciistl"],
+            ExportFormat::ThreeMf => vec![],
+            ExportFormat::Png => vec!["--viewall", "--autocenter", "--imgsize=1024,768"],
+        }
+    }
+}
+
+/// Errors raised while exporting trophies with OpenSCAD.
+#[derive(thiserror::Error, Debug)]
+pub enum OpenScadError {
+    #[error("OpenSCAD executable not found: {0}")]
+    NotFound(PathBuf),
+    #[error("OpenSCAD failed ({status}): {stderr}")]
+    Failed { status: ExitStatus, stderr: String },
+    #[error("OpenSCAD exported an invalid STL file")]
+    InvalidStl,
+    #[error("I/O error while running OpenSCAD: {0}")]
+    Io(#[from] std::io::Error),
+}
+
+/// OpenSCAD executable used to export trophies.
+#[derive(Debug, Clone)]
+pub struct OpenScad {
+    pub executable: PathBuf,
+}
+
+impl Default for OpenScad {
+    /// The `openscad` executable found in the `PATH`.
+    fn default() -> Self {
+        OpenScad {
+            executable: PathBuf::from("openscad"),
+        }
+    }
+}
+
+impl OpenScad {
+    pub fn new(executable: impl Into<PathBuf>) -> Self {
+        OpenScad {
+            executable: executable.into(),
+        }
+    }
+
+    /// Export the trophy described by `template`, the content of the
+    /// template file `template_name`, e.g: `comparison.scad`, for the
+    /// program `data` generated without static code. Both are written to
+    /// a temporary directory, as `activity-data.scad` is included by the
+    /// template, along with the GitHub logo it imports. Fails with
+    /// [`OpenScadError::Failed`], reporting the standard error of
+    /// OpenSCAD, if the export fails.
+    pub fn export(
+        &self,
+        template_name: &str,
+        template: &str,
+        data: &str,
+        format: ExportFormat,
+    ) -> Result<Vec<u8>, OpenScadError> {
+        let dir = tempfile::tempdir()?;
+        let template_path = write_bundle(dir.path(), template_name, template, data)?;
+
+        let output_path = template_path.with_extension(format.extension());
+        self.run(&template_path, &output_path, format, &[])?;
+        Ok(std::fs::read(&output_path)?)
+    }
+
+    /// Export the meshes of the trophy of [`export`](Self::export) once
+    /// per set of `variables`, OpenSCAD variables and expressions
+    /// overriding those of the template and of the data, e.g: `part` and
+    /// `"logo"` to export a single part of the trophy.
+    pub fn export_meshes(
+        &self,
+        template_name: &str,
+        template: &str,
+        data: &str,
+        variables: &[Vec<(String, String)>],
+    ) -> Result<Vec<Mesh>, OpenScadError> {
+        let dir = tempfile::tempdir()?;
+        let template_path = write_bundle(dir.path(), template_name, template, data)?;
+
+        let output_path = template_path.with_extension("stl");
+        let mut meshes = Vec::with_capacity(variables.len());
+        for variables in variables {
+            self.run(
+                &template_path,
+                &output_path,
+                ExportFormat::BinaryStl,
+                variables,
+            )?;
+            let mesh =
+                from_binary_stl(&std::fs::read(&output_path)?).ok_or(OpenScadError::InvalidStl)?;
+            meshes.push(mesh);
+        }
+        Ok(meshes)
+    }
+
+    fn run(
+        &self,
+        input: &Path,
+        output: &Path,
+        format: ExportFormat,
+        variables: &[(String, String)],
+    ) -> Result<(), OpenScadError> {
+        let definitions = variables
+            .iter()
+            .flat_map(|(name, expression)| ["-D".to_string(), format!("{}={}", name, expression)]);
+        let result = Command::new(&self.executable)
+            .arg("-o")
+            .arg(output)
+            .args(format.arguments())
+            .args(definitions)
+            .arg(input)
+            .output();
+        let result = match result {
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => {
+                return Err(OpenScadError::NotFound(self.executable.clone()));
+            }
+            result => result?,
+        };
+        if !result.status.success() {
+            return Err(OpenScadError::Failed {
+                status: result.status,
+                stderr: String::from_utf8_lossy(&result.stderr).trim().to_string(),
+            });
+        }
+        Ok(())
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::os::unix::fs::PermissionsExt;
+
+    /// Script standing for OpenSCAD, running `body` with the output path
+    /// as `$2` and the input path as the last argument.
+    fn fake_openscad(dir: &Path, body: &str) -> OpenScad {
+        let path = dir.join("openscad");
+        std::fs::write(&path, format!("#!/bin/sh\n{}\n", body)).unwrap();
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+        OpenScad::new(path)
+    }
+
+    #[test]
+    fn test_export() {
+        let dir = tempfile::tempdir().unwrap();
+        // Concatenate the input files, checking the logo is next to them
+        let openscad = fake_openscad(
+            dir.path(),
+            r#"for input; do :; done
+            cd "$(dirname "$input")" && test -s images/github.svg &&
+            cat layers.scad activity-data.scad > "$2" && echo "$3 $4" >> "$2""#,
+        );
+
+        let exported = openscad
+            .export(
+                "layers.scad",
+                "template\n",
+                "data\n",
+                ExportFormat::BinaryStl,
+            )
+            .unwrap();
+        assert_eq!(
+            String::from_utf8(exported).unwrap(),
+            "template\ndata\n--export-format binstl\n"
+        );
+    }
+
+    #[test]
+    fn test_export_meshes() {
+        let dir = tempfile::tempdir().unwrap();
+        let stl_path = dir.path().join("triangle.stl");
+        let triangle = Mesh {
+            vertices: vec![[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [0.0, 1.5, 0.0]],
+            triangles: vec![[0, 1, 2]],
+        };
+        std::fs::write(&stl_path, crate::mesh::stl::to_binary_stl(&triangle)).unwrap();
+        // Export the same triangle, logging the variables of each export
+        let log_path = dir.path().join("variables.log");
+        let openscad = fake_openscad(
+            dir.path(),
+            &format!(
+                r#"echo "$5 $6" >> {log} && cp {stl} "$2""#,
+                log = log_path.display(),
+                stl = stl_path.display()
+            ),
+        );
+
+        let variables =
+            ["logo", "text"].map(|part| vec![("part".to_string(), format!("\"{}\"", part))]);
+        let meshes = openscad
+            .export_meshes("trophy.scad", "template", "data", &variables)
+            .unwrap();
+        assert_eq!(meshes, vec![triangle.clone(), triangle]);
+        assert_eq!(
+            std::fs::read_to_string(&log_path).unwrap(),
+            "-D part=\"logo\"\n-D part=\"text\"\n"
+        );
+    }
+
+    #[test]
+    fn test_export_meshes_invalid_stl() {
+        let dir = tempfile::tempdir().unwrap();
+        let openscad = fake_openscad(dir.path(), r#"echo "not an STL file" > "$2""#);
+
+        let variables = vec![vec![("part".to_string(), "\"base\"".to_string())]];
+        assert!(matches!(
+            openscad.export_meshes("trophy.scad", "template", "data", &variables),
+            Err(OpenScadError::InvalidStl)
+        ));
+    }
+
+    #[test]
+    fn test_export_failure() {
+        let dir = tempfile::tempdir().unwrap();
+        let openscad = fake_openscad(dir.path(), "echo 'Parser error' >&2; exit 1");
+
+        let error = openscad
+            .export("trophy.scad", "template", "data", ExportFormat::Png)
+            .unwrap_err();
+        match error {
+            OpenScadError::Failed { status, stderr } => {
+                assert_eq!(status.code(), Some(1));
+                assert_eq!(stderr, "Parser error");
+            }
+            error => panic!("unexpected error: {}", error),
+        }
+    }
+
+    #[test]
+    fn test_export_not_found() {
+        let openscad = OpenScad::new("/nonexistent/openscad");
+        assert!(matches!(
+            openscad.export("trophy.scad", "template", "data", ExportFormat::ThreeMf),
+            Err(OpenScadError::NotFound(_))
+        ));
+    }
+}
@@ -0,0 +1,303 @@
+use chrono::{Datelike, NaiveDate, Weekday};
+use serde::Serialize;
+
+use crate::github::activity::{Activity, WeekStart};
+
+/// Run of consecutive days with contributions.
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Streak {
+    pub start: NaiveDate,
+    pub end: NaiveDate,
+}
+
+impl Streak {
+    /// Number of days in the streak.
+    pub fn days(&self) -> u32 {
+        (self.end - self.start).num_days() as u32 + 1
+    }
+}
+
+/// Contributions made on a day or week.
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Peak {
+    /// The day, or the Monday of the week.
+    pub date: NaiveDate,
+    pub contributions: u32,
+}
+
+/// Summary of the contributions in an [`Activity`].
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct ActivityStats {
+    pub total_contributions: u32,
+    /// Days with at least one contribution.
+    pub active_days: u32,
+    pub average_per_active_day: f64,
+    /// Longest streak, the earliest one if several are equally long.
+    pub longest_streak: Option<Streak>,
+    /// Streak ending on the last day of the activity, or on the day
+    /// before if no contributions were made yet on the last day.
+    pub current_streak: Option<Streak>,
+    pub busiest_day: Option<Peak>,
+    /// Busiest week, Monday to Sunday, only counting days in the activity
+    /// date range.
+    pub busiest_week: Option<Peak>,
+    /// Contributions made on each day of the week, Monday first.
+    pub weekday_contributions: [u32; 7],
+}
+
+impl ActivityStats {
+    pub fn from_activity(activity: &Activity) -> Self {
+        let (start, end) = activity.date_range;
+        let days: Vec<(NaiveDate, u32)> = start
+            .iter_days()
+            .take_while(|date| *date <= end)
+            .map(|date| (date, activity.contributions_on(date)))
+            .collect();
+
+        let total_contributions = days.iter().map(|(_, count)| count).sum();
+        let active_days = days.iter().filter(|(_, count)| *count > 0).count() as u32;
+        let average_per_active_day = if active_days == 0 {
+            0.0
+        } else {
+            total_contributions as f64 / active_days as f64
+        };
+
+        let mut streaks: Vec<Streak> = Vec::new();
+        for (date, count) in days.iter().copied() {
+            if count == 0 {
+                continue;
+            }
+            match streaks.last_mut() {
+                Some(streak) if streak.end.succ_opt() == Some(date) => streak.end = date,
+                _ => streaks.push(Streak {
+                    start: date,
+                    end: date,
+                }),
+            }
+        }
+        let longest_streak = streaks.iter().copied().reduce(|longest, streak| {
+            if streak.days() > longest.days() {
+                streak
+            } else {
+                longest
+            }
+        });
+        let current_streak = streaks
+            .last()
+            .copied()
+            .filter(|streak| streak.end == end || streak.end.succ_opt() == Some(end));
+
+        let busiest_day = days
+            .iter()
+            .copied()
+            .filter(|(_, count)| *count > 0)
+            .reduce(|busiest, day| if day.1 > busiest.1 { day } else { busiest })
+            .map(|(date, contributions)| Peak {
+                date,
+                contributions,
+            });
+
+        let mut weeks: Vec<Peak> = Vec::new();
+        for (date, count) in days.iter().copied() {
+            let monday = WeekStart::Monday.first_day_of_week(date);
+            match weeks.last_mut() {
+                Some(week) if week.date == monday => week.contributions += count,
+                _ => weeks.push(Peak {
+                    date: monday,
+                    contributions: count,
+                }),
+            }
+        }
+        let busiest_week = weeks
+            .into_iter()
+            .filter(|week| week.contributions > 0)
+            .reduce(|busiest, week| {
+                if week.contributions > busiest.contributions {
+                    week
+                } else {
+                    busiest
+                }
+            });
+
+        let mut weekday_contributions = [0; 7];
+        for (date, count) in days.iter() {
+            weekday_contributions[date.weekday().num_days_from_monday() as usize] += count;
+        }
+
+        ActivityStats {
+            total_contributions,
+            active_days,
+            average_per_active_day,
+            longest_streak,
+            current_streak,
+            busiest_day,
+            busiest_week,
+            weekday_contributions,
+        }
+    }
+
+    /// Short summary to be embossed on trophies, e.g:
+    /// "1,234 contributions · 87-day streak".
+    pub fn summary(&self) -> String {
+        let contributions = format!(
+            "{} contributions",
+            with_thousands_separator(self.total_contributions)
+        );
+        match self.longest_streak {
+            Some(streak) => format!("{} · {}-day streak", contributions, streak.days()),
+            None => contributions,
+        }
+    }
+}
+
+/// Format `value` grouping digits in thousands, e.g: 1,234.
+fn with_thousands_separator(value: u32) -> String {
+    let digits = value.to_string();
+    let mut builder = String::new();
+    for (i, digit) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            builder.push(',');
+        }
+        builder.push(digit);
+    }
+    builder
+}
+
+fn format_streak(maybe_streak: Option<Streak>) -> String {
+    match maybe_streak {
+        Some(streak) => format!("{} days ({} - {})", streak.days(), streak.start, streak.end),
+        None => "none".to_string(),
+    }
+}
+
+impl std::fmt::Display for ActivityStats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(
+            f,
+            "Total contributions: {}",
+            with_thousands_separator(self.total_contributions)
+        )?;
+        writeln!(
+            f,
+            "Active days: {} ({:.2} contributions per active day)",
+            self.active_days, self.average_per_active_day
+        )?;
+        writeln!(f, "Longest streak: {}", format_streak(self.longest_streak))?;
+        writeln!(f, "Current streak: {}", format_streak(self.current_streak))?;
+        if let Some(day) = self.busiest_day {
+            writeln!(
+                f,
+                "Busiest day: {} ({} contributions)",
+                day.date, day.contributions
+            )?;
+        }
+        if let Some(week) = self.busiest_week {
+            writeln!(
+                f,
+                "Busiest week: week of {} ({} contributions)",
+                week.date, week.contributions
+            )?;
+        }
+        writeln!(f, "Contributions by day of the week:")?;
+        for (i, count) in self.weekday_contributions.iter().enumerate() {
+            let weekday = Weekday::try_from(i as u8).unwrap();
+            writeln!(f, "  {}: {}", weekday, count)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2024, month, day).unwrap()
+    }
+
+    #[test]
+    fn test_stats() {
+        // Monday 2024-01-01 to Wednesday 2024-01-17
+        let activity = Activity::from_daily_contributions(
+            (date(1, 1), date(1, 17)),
+            vec![
+                (date(1, 1), 1),
+                (date(1, 2), 2),
+                (date(1, 3), 3),
+                (date(1, 8), 10),
+                (date(1, 15), 1),
+                (date(1, 16), 1),
+            ],
+        );
+        let stats = ActivityStats::from_activity(&activity);
+
+        assert_eq!(stats.total_contributions, 18);
+        assert_eq!(stats.active_days, 6);
+        assert_eq!(stats.average_per_active_day, 3.0);
+        assert_eq!(
+            stats.longest_streak,
+            Some(Streak {
+                start: date(1, 1),
+                end: date(1, 3)
+            })
+        );
+        // No contributions yet on the last day
+        assert_eq!(
+            stats.current_streak,
+            Some(Streak {
+                start: date(1, 15),
+                end: date(1, 16)
+            })
+        );
+        assert_eq!(
+            stats.busiest_day,
+            Some(Peak {
+                date: date(1, 8),
+                contributions: 10
+            })
+        );
+        assert_eq!(
+            stats.busiest_week,
+            Some(Peak {
+                date: date(1, 8),
+                contributions: 10
+            })
+        );
+        assert_eq!(stats.weekday_contributions, [12, 3, 3, 0, 0, 0, 0]);
+        assert_eq!(stats.summary(), "18 contributions · 3-day streak");
+    }
+
+    #[test]
+    fn test_stats_without_contributions() {
+        let activity = Activity::from_daily_contributions((date(1, 1), date(1, 7)), vec![]);
+        let stats = ActivityStats::from_activity(&activity);
+
+        assert_eq!(stats.total_contributions, 0);
+        assert_eq!(stats.average_per_active_day, 0.0);
+        assert_eq!(stats.longest_streak, None);
+        assert_eq!(stats.current_streak, None);
+        assert_eq!(stats.busiest_day, None);
+        assert_eq!(stats.busiest_week, None);
+        assert_eq!(stats.summary(), "0 contributions");
+    }
+
+    #[test]
+    fn test_stats_broken_current_streak() {
+        let activity = Activity::from_daily_contributions(
+            (date(1, 1), date(1, 7)),
+            vec![(date(1, 1), 1), (date(1, 2), 1)],
+        );
+        let stats = ActivityStats::from_activity(&activity);
+
+        assert_eq!(stats.longest_streak.unwrap().days(), 2);
+        assert_eq!(stats.current_streak, None);
+    }
+
+    #[test]
+    fn test_with_thousands_separator() {
+        assert_eq!(with_thousands_separator(0), "0");
+        assert_eq!(with_thousands_separator(999), "999");
+        assert_eq!(with_thousands_separator(1234), "1,234");
+        assert_eq!(with_thousands_separator(1234567), "1,234,567");
+    }
+}